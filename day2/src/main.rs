@@ -1,4 +1,4 @@
-mod lib;
+use day2 as lib;
 
 fn main() { 
     lib::test_part_one();
@@ -1,5 +1,6 @@
-mod lib;
-mod parser;
+use day5 as lib;
+
+use intcode::parser;
 
 fn main() {
     let op_codes = parser::parse_file("data/05.dat".to_string());
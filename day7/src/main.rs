@@ -1,85 +1,154 @@
 // https://adventofcode.com/2019/day/7
-mod lib;
-mod parser;
+use intcode::network::{Network, NetworkError};
+use intcode::{parser, MemHandler};
 use permutohedron::Heap;
+use std::thread;
+
+// Per amplifier, far more than any phase setting needs
+const MAX_INSTRUCTIONS: u64 = 10_000_000;
 
 fn main() {
-    let res = find_max_signal(&mut [0, 1, 2, 3, 4]);
-    println!("Part one: {}", res);
-    let res = find_max_signal(&mut [5, 6, 7, 8, 9]);
-    println!("Part one: {}", res);
+    let program = parser::parse_file("data/07.dat".to_string());
+
+    let (signal, phases) = find_max_signal(&program, &[0, 1, 2, 3, 4]).expect("Amplifier failed");
+    println!("Part one: {} with phases {:?}", signal, phases);
+    let (signal, phases) = find_max_signal(&program, &[5, 6, 7, 8, 9]).expect("Amplifier failed");
+    println!("Part two: {} with phases {:?}", signal, phases);
 }
 
-fn find_max_signal(phase_setting: &mut [i64; 5]) -> i64 {
-    let op_codes = parser::parse_file("data/07.dat".to_string());
-    let mut record = 0;
-    let perms = Heap::new(phase_setting);
+// Highest signal so far and the phase sequence giving it
+type Record = Option<(i64, Vec<i64>)>;
 
-    for perm in perms {
-        let res = max_of_single_phase(&perm, op_codes.clone()).unwrap();
-        if res > record {
-            record = res;
+// Tries every order of the phase settings, spread over one worker thread per
+// core, and returns the highest signal with the phase sequence giving it.
+// Ties go to the sequence found first by Heap's algorithm.
+fn find_max_signal(
+    program: &[i64],
+    phase_setting: &[i64],
+) -> Result<(i64, Vec<i64>), NetworkError> {
+    let mut phases = phase_setting.to_vec();
+    let perms: Vec<Vec<i64>> = Heap::new(&mut phases).collect();
+
+    let workers = thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk_size = perms.len().div_ceil(workers);
+
+    let results: Vec<Result<Record, NetworkError>> = thread::scope(|scope| {
+        let handles: Vec<_> = perms
+            .chunks(chunk_size.max(1))
+            .map(|chunk| scope.spawn(move || best_of(program, chunk)))
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+
+    let mut record: Record = None;
+    for result in results {
+        if let Some((signal, phases)) = result? {
+            if record.as_ref().is_none_or(|(best, _)| signal > *best) {
+                record = Some((signal, phases));
+            }
         }
     }
-    return record;
+    Ok(record.unwrap_or_default())
 }
 
-fn max_of_single_phase(phase_sequence: &[i64; 5], program: Vec<i64>) -> Option<i64> {
-    let mut computers = vec![
-        lib::MemHandler::new(program.clone()),
-        lib::MemHandler::new(program.clone()),
-        lib::MemHandler::new(program.clone()),
-        lib::MemHandler::new(program.clone()),
-        lib::MemHandler::new(program.clone()),
-    ];
-
-    for i in 0..computers.len() {
-        computers[i].add_input(phase_sequence[i]);
-    }
-
-    let mut finished: [bool; 5] = [false; 5];
-    let mut prev_val = Some(0);
-    while finished.iter().any(|f| *f == false) {
-        for i in 0..computers.len() {
-            if let Some(val) = prev_val {
-                computers[i].add_input(val);
-            }
-            prev_val = computers[i].run();
-            finished[i] = computers[i].finished();
+fn best_of(program: &[i64], perms: &[Vec<i64>]) -> Result<Record, NetworkError> {
+    let mut record: Record = None;
+    for perm in perms {
+        let signal = max_of_single_phase(perm, program.to_vec())?;
+        if record.as_ref().is_none_or(|(best, _)| signal > *best) {
+            record = Some((signal, perm.clone()));
         }
     }
+    Ok(record)
+}
 
-    return computers[4].get_result();
+fn max_of_single_phase(phase_sequence: &[i64], program: Vec<i64>) -> Result<i64, NetworkError> {
+    let amplifiers = phase_sequence
+        .iter()
+        .map(|_| {
+            let mut amplifier = MemHandler::new(program.clone());
+            amplifier.set_instruction_budget(MAX_INSTRUCTIONS);
+            amplifier.detect_no_progress();
+            amplifier
+        })
+        .collect();
+
+    // The last signal is fed back to the first amplifier, amplifiers that
+    // only produce a single signal simply halt after it
+    let mut network = Network::ring(amplifiers);
+    for (amplifier, phase) in phase_sequence.iter().enumerate() {
+        network.add_input(amplifier, *phase);
+    }
+    network.add_input(0, 0);
+    network.run()
 }
 
 #[test]
 fn test_examples() {
-    let res = max_signal_from_phase(
+    let res = max_of_single_phase(
         &[4, 3, 2, 1, 0],
-        parse_str_to_int("3,15,3,16,1002,16,10,16,1,16,15,15,4,15,99,0,0"),
+        parser::parse_str("3,15,3,16,1002,16,10,16,1,16,15,15,4,15,99,0,0"),
     );
 
-    assert!(res == 43210);
-    let res = max_signal_from_phase(
+    assert!(res == Ok(43210));
+    let res = max_of_single_phase(
         &[0, 1, 2, 3, 4],
-        parse_str_to_int(
+        parser::parse_str(
             "3,23,3,24,1002,24,10,24,1002,23,-1,23,101,5,23,23,1,24,23,23,4,23,99,0,0",
         ),
     );
 
-    assert!(res == 54321);
-    let res = max_signal_from_phase(
+    assert!(res == Ok(54321));
+    let res = max_of_single_phase(
         &[1, 0, 4, 3, 2],
-        parse_str_to_int("3,31,3,32,1002,32,10,32,1001,31,-2,31,1007,31,0,33,1002,33,7,33,1,33,31,31,1,32,31,31,4,31,99,0,0,0")
+        parser::parse_str("3,31,3,32,1002,32,10,32,1001,31,-2,31,1007,31,0,33,1002,33,7,33,1,33,31,31,1,32,31,31,4,31,99,0,0,0")
     );
-    assert!(res == 65210);
+    assert!(res == Ok(65210));
 }
 
-#[allow(dead_code)]
-fn parse_str_to_int(input: &str) -> Vec<i64> {
-    input
-        .to_string()
-        .split(",")
-        .map(|n| n.parse::<i64>().unwrap())
-        .collect()
+#[test]
+fn test_feedback_loop() {
+    let res = max_of_single_phase(
+        &[9, 8, 7, 6, 5],
+        parser::parse_str(
+            "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5",
+        ),
+    );
+    assert!(res == Ok(139629729));
+}
+
+#[test]
+fn test_stuck_amplifiers() {
+    use intcode::RunStatus;
+
+    // Reads the phase and then loops forever
+    let res = max_of_single_phase(&[0, 1, 2, 3, 4], parser::parse_str("3,9,1105,1,2,99"));
+    assert!(
+        res == Err(NetworkError::Stopped {
+            node: 0,
+            status: RunStatus::NoProgress
+        })
+    );
+
+    // Reads the phase and halts without ever reading a signal
+    let res = max_of_single_phase(&[5, 6, 7, 8, 9], parser::parse_str("3,3,99,0"));
+    assert!(res == Err(NetworkError::NoSignal));
+
+    // Waits for a second signal that never comes
+    let res = max_of_single_phase(&[0, 1, 2, 3, 4], parser::parse_str("3,9,3,9,3,9,99"));
+    // Only the first amplifier got a signal
+    let blocked = vec![(0, 4), (1, 2), (2, 2), (3, 2), (4, 2)];
+    assert!(res == Err(NetworkError::Deadlock { blocked }));
+}
+
+#[test]
+fn test_find_max_signal() {
+    let program = parser::parse_str("3,15,3,16,1002,16,10,16,1,16,15,15,4,15,99,0,0");
+    assert!(find_max_signal(&program, &[0, 1, 2, 3, 4]) == Ok((43210, vec![4, 3, 2, 1, 0])));
+
+    let program = parser::parse_str(
+        "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5",
+    );
+    let res = find_max_signal(&program, &[5, 6, 7, 8, 9]);
+    assert!(res == Ok((139629729, vec![9, 8, 7, 6, 5])));
 }
@@ -0,0 +1,34 @@
+// Times part two with and without the decode cache:
+//
+//     cargo run --release --example bench
+use intcode::{parser, MemHandler, RunStatus};
+use std::time::{Duration, Instant};
+
+const RUNS: u32 = 20;
+
+fn time_part_two(program: &[i64], decode_cache: bool) -> Duration {
+    let start = Instant::now();
+    for _ in 0..RUNS {
+        let mut mem_handler = MemHandler::new(program.to_vec());
+        if decode_cache {
+            mem_handler.enable_decode_cache();
+        }
+        mem_handler.add_input(2);
+        assert!(mem_handler.run_to_halt() == Ok(RunStatus::Halted));
+        assert!(mem_handler.get_result() == Some(vec![47253]));
+    }
+    start.elapsed() / RUNS
+}
+
+fn main() {
+    let program = parser::parse_file("data/09.dat".to_string());
+
+    let plain = time_part_two(&program, false);
+    let cached = time_part_two(&program, true);
+    println!("interpreter:  {:?} per run", plain);
+    println!("decode cache: {:?} per run", cached);
+    println!(
+        "speedup:      {:.2}x",
+        plain.as_secs_f64() / cached.as_secs_f64()
+    );
+}
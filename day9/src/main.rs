@@ -1,32 +1,39 @@
-use std::fs;
+use intcode::device::{self, FixedInput, Recorder};
+use intcode::{parser, MemHandler, RunStatus};
 
-mod lib;
+// Generous for BOOST, which runs a few hundred thousand instructions, but
+// stops a broken program long before it looks hung
+const MAX_INSTRUCTIONS: u64 = 1_000_000_000;
 
 fn main() {
-    let input: Vec<i64> = fs::read_to_string("data/09.dat")
-        .expect("Something went wrong reading the file")
-        .lines()
-        .map(|l| l.split(",").map(|c| c.parse::<i64>().unwrap()))
-        .flatten()
-        .collect();
+    let input = parser::parse_file("data/09.dat".to_string());
 
     println!("Result part one: {:?}", execute(input.clone(), 1));
     println!("Result part two: {:?}", execute(input.clone(), 2));
 }
 
 fn execute(codes: Vec<i64>, input: i64) -> Vec<i64> {
-    let mut mem_handler = lib::MemHandler::new(codes);
-    mem_handler.add_input(input);
-    while !mem_handler.finished() {
-        mem_handler.run();
+    let mut mem_handler = MemHandler::new(codes);
+    mem_handler.set_instruction_budget(MAX_INSTRUCTIONS);
+    mem_handler.detect_no_progress();
+    mem_handler.enable_decode_cache();
+    let device = device::attach(
+        &mut mem_handler,
+        Recorder::new(FixedInput::new(vec![input])),
+    );
+    match mem_handler.run_to_halt() {
+        Ok(RunStatus::Halted) => {}
+        Ok(status) => panic!("Intcode program stopped: {:?}", status),
+        Err(err) => panic!("Intcode program failed: {}", err),
     }
 
-    return mem_handler.get_result().unwrap();
+    let outputs = device.lock().unwrap().outputs();
+    outputs
 }
 
 #[test]
 fn test_one() {
-    let mut input = vec![
+    let input = vec![
         109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
     ];
 
@@ -37,15 +44,15 @@ fn test_one() {
 
 #[test]
 fn test_two() {
-    let mut input = vec![1102, 34915192, 34915192, 7, 4, 7, 99, 0];
+    let input = vec![1102, 34915192, 34915192, 7, 4, 7, 99, 0];
 
     let output = execute(input.clone(), 0)[0];
 
-    assert!(output < 10000000000000000 && output >= 1000000000000000);
+    assert!((1000000000000000..10000000000000000).contains(&output));
 }
 #[test]
 fn test_three() {
-    let mut input = vec![104, 1125899906842624, 99];
+    let input = vec![104, 1125899906842624, 99];
 
     let output = execute(input.clone(), 0)[0];
 
@@ -0,0 +1,17 @@
+// What add and multiply do when the result does not fit in the word type,
+// see Word. Programs that need the exact result can run with wider words
+// instead, or start out with i64 words and switch over when needed.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Arithmetic {
+    // Wrap around, like plain i64 arithmetic in a release build
+    #[default]
+    Wrapping,
+    // Fail with VmError::Overflow
+    Checked,
+    // Clamp to the smallest or largest value
+    Saturating,
+    // Stop with RunStatus::NeedsWiderWords without running the instruction,
+    // MemHandler::widen then continues with i128 words. Words that can not
+    // be widened any further, i.e. i128, overflow like Checked.
+    Widening,
+}
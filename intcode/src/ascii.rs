@@ -0,0 +1,152 @@
+// ASCII I/O for programs that talk in character codes, e.g. text adventures
+// or programs drawing pictures. Text is queued as input one code per byte,
+// output is split into text and the values outside the ASCII range, which
+// such programs use for answers that do not fit in a character.
+//
+// interact runs a program as a terminal session, see the ascii binary.
+use crate::error::VmError;
+use crate::mem_handler::{MemHandler, RunStatus};
+use crate::memory::Memory;
+use crate::word::Word;
+use std::io::{self, BufRead, Write};
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AsciiOutput<W = i64> {
+    pub text: String,
+    // Values that are not ASCII codes, in the order they were produced
+    pub values: Vec<W>,
+}
+
+fn as_ascii<W: Word>(val: &W) -> Option<char> {
+    val.to_i64()
+        .filter(|code| (0..128).contains(code))
+        .map(|code| code as u8 as char)
+}
+
+// Queues text as input, one character code per byte
+pub fn add_str<M: Memory>(mem_handler: &mut MemHandler<M>, text: &str) {
+    for byte in text.bytes() {
+        mem_handler.add_input(M::Word::from(byte as i64));
+    }
+}
+
+pub fn split_output<W: Word>(output: &[W]) -> AsciiOutput<W> {
+    let mut split = AsciiOutput {
+        text: String::new(),
+        values: vec![],
+    };
+    for val in output {
+        match as_ascii(val) {
+            Some(c) => split.text.push(c),
+            None => split.values.push(val.clone()),
+        }
+    }
+    split
+}
+
+// Everything the program has output so far
+pub fn output<M: Memory>(mem_handler: &MemHandler<M>) -> AsciiOutput<M::Word> {
+    split_output(&mem_handler.get_result().unwrap_or_default())
+}
+
+// Why a terminal session ended
+#[derive(Debug)]
+pub enum SessionEnd<W = i64> {
+    Halted,
+    // Needed input but there were no more lines
+    InputClosed,
+    // Stopped with a status the session can not handle, e.g. a watchpoint
+    Stopped(RunStatus<W>),
+    Fault(VmError),
+    Io(io::Error),
+}
+
+// Runs the program as a terminal session. Text is written to output as it
+// is produced and values that are not ASCII on a line of their own. Every
+// time the program needs input a line is read from input and queued,
+// including its newline.
+pub fn interact<M, R, W>(
+    mem_handler: &mut MemHandler<M>,
+    mut input: R,
+    mut output: W,
+) -> SessionEnd<M::Word>
+where
+    M: Memory,
+    R: BufRead,
+    W: Write,
+{
+    let mut line_start = true;
+    loop {
+        let result = match mem_handler.run_until_event() {
+            Ok(RunStatus::Output(val)) => match as_ascii(&val) {
+                Some(c) => {
+                    line_start = c == '\n';
+                    write!(output, "{}", c)
+                }
+                None => {
+                    let newline = if line_start { "" } else { "\n" };
+                    line_start = true;
+                    writeln!(output, "{}{}", newline, val)
+                }
+            },
+            Ok(RunStatus::NeedsInput) => {
+                let mut line = String::new();
+                match output.flush().and_then(|_| input.read_line(&mut line)) {
+                    Ok(0) => return SessionEnd::InputClosed,
+                    Ok(_) => {
+                        if !line.ends_with('\n') {
+                            line.push('\n');
+                        }
+                        add_str(mem_handler, &line);
+                        Ok(())
+                    }
+                    Err(err) => Err(err),
+                }
+            }
+            Ok(RunStatus::Halted) => {
+                return match output.flush() {
+                    Ok(()) => SessionEnd::Halted,
+                    Err(err) => SessionEnd::Io(err),
+                }
+            }
+            Ok(status) => return SessionEnd::Stopped(status),
+            Err(err) => return SessionEnd::Fault(err),
+        };
+
+        if let Err(err) = result {
+            return SessionEnd::Io(err);
+        }
+    }
+}
+
+#[test]
+fn test_ascii() {
+    // Outputs "Hi", a newline and a score of 1000
+    let mut mem_handler = MemHandler::new(vec![104, 72, 104, 105, 104, 10, 104, 1000, 99]);
+    mem_handler.run_to_halt().unwrap();
+    let split = output(&mem_handler);
+    assert!(split.text == "Hi\n" && split.values == vec![1000]);
+    assert!(split_output(&[-1_i64, 65, 128]).text == "A");
+
+    let mut mem_handler = MemHandler::new(vec![99]);
+    add_str(&mut mem_handler, "go n\n");
+    assert!(mem_handler.pending_input() == vec![103, 111, 32, 110, 10]);
+}
+
+#[test]
+fn test_interact() {
+    // Echoes every character
+    let echo = vec![3, 100, 4, 100, 1105, 1, 0];
+    let mut mem_handler = MemHandler::new(echo);
+    let mut out = vec![];
+    let end = interact(&mut mem_handler, &b"north\ntake lamp"[..], &mut out);
+    assert!(matches!(end, SessionEnd::InputClosed));
+    assert!(out == b"north\ntake lamp\n");
+
+    // A score in the middle of a line goes on a line of its own
+    let mut mem_handler = MemHandler::new(vec![104, 62, 104, 1000, 104, 10, 99]);
+    let mut out = vec![];
+    let end = interact(&mut mem_handler, &b""[..], &mut out);
+    assert!(matches!(end, SessionEnd::Halted));
+    assert!(out == b">\n1000\n\n");
+}
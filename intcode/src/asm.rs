@@ -0,0 +1,286 @@
+// Assembler, turns mnemonics into a program that can be written with
+// parser::to_str. Example:
+//
+//     ; Outputs the numbers 10 down to 1
+//     loop:   out [counter]
+//             add [counter], #-1 -> [counter]
+//             jt [counter], #loop
+//             hlt
+//     counter: data 10
+//
+// Mnemonics are the ones printed by the disassembler (add, mul, in, out, jt,
+// jf, lt, eq, arb and hlt) in any case. Parameters are written as [pos] for
+// position mode, #imm for immediate mode and [rb+n] (or [r+n]) for relative
+// mode. Parameters are separated by ',' and the write target may also be
+// separated by '->'. Values are numbers or labels, optionally followed by
+// +n or -n. `data` emits its comma separated values as is.
+//
+// Everything after ';' is a comment. Numeric labels, like the addresses in a
+// disassembler listing, are ignored so a listing can be assembled again.
+use crate::op_code::{OpCode, ParamMode};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AsmError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for AsmError {}
+
+// A number or a label with an offset, resolved once all labels are known
+#[derive(Clone, Debug, PartialEq)]
+enum Value {
+    Number(i64),
+    Label(String, i64),
+}
+
+enum Item {
+    Instruction(OpCode, Vec<(ParamMode, Value)>),
+    Data(Vec<Value>),
+}
+
+impl Item {
+    fn len(&self) -> usize {
+        match self {
+            Item::Instruction(_, params) => params.len() + 1,
+            Item::Data(values) => values.len(),
+        }
+    }
+}
+
+pub fn assemble(source: &str) -> Result<Vec<i64>, AsmError> {
+    let mut labels = HashMap::new();
+    let mut items = Vec::new();
+    let mut address = 0;
+
+    for (index, line) in source.lines().enumerate() {
+        let line_nr = index + 1;
+        let error = |message: String| AsmError {
+            line: line_nr,
+            message,
+        };
+
+        let (line_labels, item) = parse_line(line).map_err(error)?;
+        for label in line_labels {
+            if labels.insert(label.clone(), address).is_some() {
+                return Err(error(format!("duplicate label '{}'", label)));
+            }
+        }
+        if let Some(item) = item {
+            address += item.len();
+            items.push((line_nr, item));
+        }
+    }
+
+    let mut program = Vec::with_capacity(address);
+    for (line_nr, item) in items {
+        let resolve = |value: &Value| match value {
+            Value::Number(n) => Ok(*n),
+            Value::Label(label, offset) => match labels.get(label) {
+                Some(address) => Ok(*address as i64 + offset),
+                None => Err(AsmError {
+                    line: line_nr,
+                    message: format!("unknown label '{}'", label),
+                }),
+            },
+        };
+
+        match item {
+            Item::Instruction(op_code, params) => {
+                let modes = params
+                    .iter()
+                    .rev()
+                    .fold(0, |modes, (mode, _)| modes * 10 + mode.to_num());
+                program.push(modes * 100 + op_code.to_num());
+                for (_, value) in params.iter() {
+                    program.push(resolve(value)?);
+                }
+            }
+            Item::Data(values) => {
+                for value in values.iter() {
+                    program.push(resolve(value)?);
+                }
+            }
+        }
+    }
+
+    Ok(program)
+}
+
+fn parse_line(line: &str) -> Result<(Vec<String>, Option<Item>), String> {
+    let mut rest = match line.find(';') {
+        Some(comment) => &line[..comment],
+        None => line,
+    }
+    .trim();
+
+    let mut labels = Vec::new();
+    while let Some(colon) = rest.find(':') {
+        let label = rest[..colon].trim();
+        if label.is_empty() || !label.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            break;
+        }
+        if !label.chars().all(|c| c.is_ascii_digit()) {
+            labels.push(label.to_string());
+        }
+        rest = rest[colon + 1..].trim();
+    }
+
+    if rest.is_empty() {
+        return Ok((labels, None));
+    }
+
+    let (mnemonic, operands) = match rest.find(char::is_whitespace) {
+        Some(space) => (&rest[..space], rest[space..].trim()),
+        None => (rest, ""),
+    };
+    let operands: Vec<String> = if operands.is_empty() {
+        vec![]
+    } else {
+        operands
+            .trim_start_matches("->")
+            .replace("->", ",")
+            .split(',')
+            .map(|o| o.trim().to_string())
+            .collect()
+    };
+
+    if mnemonic.eq_ignore_ascii_case("data") || mnemonic.eq_ignore_ascii_case(".data") {
+        let values = operands
+            .iter()
+            .map(|o| parse_value(o))
+            .collect::<Result<Vec<Value>, String>>()?;
+        return Ok((labels, Some(Item::Data(values))));
+    }
+
+    let op_code = OpCode::from_mnemonic(mnemonic)
+        .ok_or_else(|| format!("unknown mnemonic '{}'", mnemonic))?;
+    if operands.len() != op_code.nr_of_params() {
+        return Err(format!(
+            "{} takes {} parameters, found {}",
+            op_code.mnemonic(),
+            op_code.nr_of_params(),
+            operands.len()
+        ));
+    }
+
+    let params = operands
+        .iter()
+        .map(|o| parse_param(o))
+        .collect::<Result<Vec<(ParamMode, Value)>, String>>()?;
+    if op_code.writes() && params.last().unwrap().0 == ParamMode::Immediate {
+        return Err(format!(
+            "{} can not write to an immediate parameter",
+            op_code.mnemonic()
+        ));
+    }
+
+    Ok((labels, Some(Item::Instruction(op_code, params))))
+}
+
+fn parse_param(param: &str) -> Result<(ParamMode, Value), String> {
+    if let Some(value) = param.strip_prefix('#') {
+        return Ok((ParamMode::Immediate, parse_value(value)?));
+    }
+
+    let inner = param
+        .strip_prefix('[')
+        .and_then(|p| p.strip_suffix(']'))
+        .ok_or_else(|| format!("bad parameter '{}'", param))?
+        .trim();
+
+    for base in ["rb", "r"].iter() {
+        if let Some(offset) = inner.strip_prefix(base) {
+            let offset = offset.trim();
+            if offset.is_empty() {
+                return Ok((ParamMode::Relative, Value::Number(0)));
+            }
+            if offset.starts_with('+') || offset.starts_with('-') {
+                let value = parse_value(offset.trim_start_matches('+'))?;
+                return Ok((ParamMode::Relative, value));
+            }
+        }
+    }
+
+    Ok((ParamMode::Position, parse_value(inner)?))
+}
+
+fn parse_value(value: &str) -> Result<Value, String> {
+    let value = value.trim();
+    if let Ok(number) = value.replace(' ', "").parse::<i64>() {
+        return Ok(Value::Number(number));
+    }
+
+    let (label, offset) = match value.find(['+', '-']) {
+        Some(sign) => {
+            let offset = value[sign..].replace(' ', "");
+            let offset = offset
+                .trim_start_matches('+')
+                .parse::<i64>()
+                .map_err(|_| format!("bad offset in '{}'", value))?;
+            (value[..sign].trim(), offset)
+        }
+        None => (value, 0),
+    };
+
+    let valid = label.starts_with(|c: char| c.is_alphabetic() || c == '_')
+        && label.chars().all(|c| c.is_alphanumeric() || c == '_');
+    if !valid {
+        return Err(format!("bad value '{}'", value));
+    }
+
+    Ok(Value::Label(label.to_string(), offset))
+}
+
+#[test]
+fn test_assemble() {
+    let program = assemble("mul [4], #3 -> [4]\ndata 33").unwrap();
+    assert!(program == vec![1002, 4, 3, 4, 33]);
+
+    let program = assemble("ADD [rb+3], #5, [100]\nARB #-1\nout [r-2]\nHLT").unwrap();
+    assert!(program == vec![1201, 3, 5, 100, 109, -1, 204, -2, 99]);
+}
+
+#[test]
+fn test_labels() {
+    let source = "
+        ; Outputs the numbers 10 down to 1
+        loop:   out [counter]
+                add [counter], #-1 -> [counter]
+                jt [counter], #loop
+                hlt
+        counter: data 10
+        table:  data counter, table+1, -7
+    ";
+    let program = assemble(source).unwrap();
+    assert!(program == vec![4, 10, 1001, 10, -1, 10, 1005, 10, 0, 99, 10, 10, 12, -7]);
+}
+
+#[test]
+fn test_errors() {
+    let error = assemble("add #1, #2 -> #3").unwrap_err();
+    assert!(error.line == 1);
+
+    let error = assemble("hlt\nfoo [1]").unwrap_err();
+    assert!(error.line == 2 && error.message == "unknown mnemonic 'foo'");
+
+    assert!(assemble("out [1], [2]").is_err());
+    assert!(assemble("jt #1, #nowhere").is_err());
+    assert!(assemble("a: hlt\na: hlt").is_err());
+}
+
+#[test]
+fn test_listing_round_trip() {
+    let program = vec![1201, 3, 5, 100, 1002, 4, 3, 4, 3, 7, 99, 33];
+    let listing = crate::disasm::listing(&program);
+    assert!(assemble(&listing) == Ok(program));
+}
@@ -0,0 +1,28 @@
+// Runs an intcode program that talks in ASCII as a terminal session, like a
+// text adventure, e.g.
+//
+//     cargo run --release --bin ascii -- adventure.dat
+//
+// Every line typed is sent to the program when it asks for input.
+use intcode::ascii::{self, SessionEnd};
+use intcode::{parser, MemHandler};
+use std::env;
+use std::io;
+use std::process;
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.len() != 1 {
+        eprintln!("Usage: ascii <file.dat>");
+        process::exit(1);
+    }
+
+    let mut mem_handler = MemHandler::new(parser::parse_file(args[0].clone()));
+    let stdin = io::stdin();
+    match ascii::interact(&mut mem_handler, stdin.lock(), io::stdout()) {
+        SessionEnd::Halted | SessionEnd::InputClosed => {}
+        SessionEnd::Stopped(status) => eprintln!("Program stopped: {:?}", status),
+        SessionEnd::Fault(err) => eprintln!("Program failed: {}", err),
+        SessionEnd::Io(err) => eprintln!("Terminal failed: {}", err),
+    }
+}
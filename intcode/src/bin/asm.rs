@@ -0,0 +1,31 @@
+// Assembles an intcode program and prints it in the comma separated format
+// read by parser::parse_file, or writes it to the given output file, e.g.
+//
+//     cargo run --bin asm -- countdown.asm data/countdown.dat
+use intcode::{asm, parser};
+use std::env;
+use std::fs;
+use std::process;
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.is_empty() || args.len() > 2 {
+        eprintln!("Usage: asm <file.asm> [file.dat]");
+        process::exit(1);
+    }
+
+    let source = fs::read_to_string(&args[0]).expect("Can not open file...");
+    let program = match asm::assemble(&source) {
+        Ok(program) => program,
+        Err(err) => {
+            eprintln!("{}: {}", args[0], err);
+            process::exit(1);
+        }
+    };
+
+    let output = parser::to_str(&program);
+    match args.get(1) {
+        Some(file) => fs::write(file, output + "\n").expect("Can not write file..."),
+        None => println!("{}", output),
+    }
+}
@@ -0,0 +1,48 @@
+// Interactive debugger for an intcode program, e.g.
+//
+//     cargo run --bin debug -- ../day9/data/09.dat 1
+//
+// Values after the file are queued as input. An empty line repeats the last
+// command.
+use intcode::debugger::{Command, Debugger};
+use intcode::{parser, MemHandler};
+use std::env;
+use std::io::{self, BufRead, Write};
+use std::process;
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.is_empty() {
+        eprintln!("Usage: debug <file.dat> [input]...");
+        process::exit(1);
+    }
+
+    let mut mem_handler = MemHandler::new(parser::parse_file(args[0].clone()));
+    for arg in args.iter().skip(1) {
+        mem_handler.add_input(arg.parse::<i64>().expect("Input must be a number"));
+    }
+
+    let mut debugger = Debugger::new(mem_handler);
+    println!("{}", debugger.execute(Command::List(1)));
+
+    let stdin = io::stdin();
+    let mut last = String::from("step");
+    loop {
+        print!("(intcode) ");
+        io::stdout().flush().unwrap();
+
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line).unwrap() == 0 {
+            break;
+        }
+        if !line.trim().is_empty() {
+            last = line.trim().to_string();
+        }
+
+        match Command::parse(&last) {
+            Ok(Command::Quit) => break,
+            Ok(command) => println!("{}", debugger.execute(command)),
+            Err(err) => println!("{}", err),
+        }
+    }
+}
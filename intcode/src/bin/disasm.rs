@@ -0,0 +1,21 @@
+// Prints a listing of every intcode program given on the command line, e.g.
+//
+//     cargo run --bin disasm -- ../day9/data/09.dat
+use intcode::{disasm, parser};
+use std::env;
+use std::process;
+
+fn main() {
+    let files: Vec<String> = env::args().skip(1).collect();
+    if files.is_empty() {
+        eprintln!("Usage: disasm <file.dat>...");
+        process::exit(1);
+    }
+
+    for file in files.iter() {
+        if files.len() > 1 {
+            println!("; {}", file);
+        }
+        print!("{}", disasm::listing(&parser::parse_file(file.clone())));
+    }
+}
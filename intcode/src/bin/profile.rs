@@ -0,0 +1,42 @@
+// Runs an intcode program with profiling enabled and prints the profile, as
+// text or as JSON with --json, e.g.
+//
+//     cargo run --release --bin profile -- ../day9/data/09.dat 2
+//     cargo run --release --bin profile -- --json ../day9/data/09.dat 2
+use intcode::{parser, MemHandler, RunStatus};
+use std::env;
+use std::process;
+
+// Number of entries shown per table in the text report
+const TOP: usize = 10;
+
+fn main() {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let json = args.first().map(|a| a == "--json").unwrap_or(false);
+    if json {
+        args.remove(0);
+    }
+    if args.is_empty() {
+        eprintln!("Usage: profile [--json] <file.dat> [input]...");
+        process::exit(1);
+    }
+
+    let mut mem_handler = MemHandler::new(parser::parse_file(args[0].clone()));
+    for arg in args[1..].iter() {
+        mem_handler.add_input(arg.parse::<i64>().expect("Input must be a number"));
+    }
+
+    mem_handler.enable_profile();
+    match mem_handler.run_to_halt() {
+        Ok(RunStatus::Halted) => {}
+        Ok(_) => eprintln!("Program is waiting for more input"),
+        Err(err) => eprintln!("Program failed: {}", err),
+    }
+
+    let profile = mem_handler.take_profile().unwrap();
+    if json {
+        println!("{}", profile.to_json());
+    } else {
+        print!("{}", profile.to_text(TOP));
+    }
+}
@@ -0,0 +1,54 @@
+// Records the execution trace of an intcode program, or checks that a fresh
+// run still matches a recorded trace, e.g.
+//
+//     cargo run --bin trace -- record ../day9/data/09.dat boost.trace 1
+//     cargo run --bin trace -- replay ../day9/data/09.dat boost.trace
+use intcode::{parser, trace};
+use std::env;
+use std::process;
+
+// Stops programs that never halt
+const MAX_STEPS: usize = 100_000_000;
+
+fn usage() -> ! {
+    eprintln!("Usage: trace record <file.dat> <file.trace> [input]...");
+    eprintln!("       trace replay <file.dat> <file.trace>");
+    process::exit(1);
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.len() < 3 {
+        usage();
+    }
+
+    let program = parser::parse_file(args[1].clone());
+    match args[0].as_str() {
+        "record" => {
+            let input: Vec<i64> = args[3..]
+                .iter()
+                .map(|a| a.parse::<i64>().expect("Input must be a number"))
+                .collect();
+            let recorded = match trace::record(program, &input, MAX_STEPS) {
+                Ok(recorded) => recorded,
+                Err(err) => {
+                    eprintln!("Program failed: {}", err);
+                    process::exit(1);
+                }
+            };
+            trace::write_file(&args[2], &recorded).expect("Can not write file...");
+            println!("Recorded {} instructions", recorded.len());
+        }
+        "replay" => {
+            let expected = trace::read_file(&args[2]).expect("Can not read trace...");
+            match trace::verify(program, &expected) {
+                Ok(()) => println!("Trace matches, {} instructions", expected.len()),
+                Err(divergence) => {
+                    println!("{}", divergence);
+                    process::exit(1);
+                }
+            }
+        }
+        _ => usage(),
+    }
+}
@@ -0,0 +1,23 @@
+// Translates an intcode program into a Rust module defining a struct that
+// runs it, printed or written to the given file, e.g.
+//
+//     cargo run --bin transpile -- ../day2/data/02.dat Gravity gravity.rs
+use intcode::{parser, transpile};
+use std::env;
+use std::fs;
+use std::process;
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.len() < 2 || args.len() > 3 {
+        eprintln!("Usage: transpile <file.dat> <StructName> [file.rs]");
+        process::exit(1);
+    }
+
+    let program = parser::parse_file(args[0].clone());
+    let source = transpile::transpile(&program, &args[1]);
+    match args.get(2) {
+        Some(file) => fs::write(file, source).expect("Can not write file..."),
+        None => print!("{}", source),
+    }
+}
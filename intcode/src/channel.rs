@@ -0,0 +1,131 @@
+// Runs a MemHandler with its input and output connected to mpsc channels, so
+// machines can run on their own threads and block while waiting for input:
+//
+//     let (to_amp, input) = mpsc::channel();
+//     let (output, from_amp) = mpsc::channel();
+//     let amp = channel::spawn(MemHandler::new(program), input, output);
+//     to_amp.send(5).unwrap();
+//     let signal = from_amp.recv().unwrap();
+//
+// A machine shuts down when it halts, or when it needs input and every sender
+// of its input channel is gone. Either way its output sender is dropped,
+// which in turn shuts down the machine reading from it. Outputs that can not
+// be delivered because the receiver is gone are still kept in the result of
+// the MemHandler.
+use crate::error::VmError;
+use crate::mem_handler::{MemHandler, RunStatus};
+use crate::memory::Memory;
+use std::sync::mpsc::{Receiver, Sender};
+use std::thread::{self, JoinHandle};
+
+// Why a machine stopped running, W is the word type of the machine
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Shutdown<W = i64> {
+    Halted,
+    // Needed input but the input channel was closed
+    InputClosed,
+    // Stopped with a status the machine can not handle itself, e.g. when its
+    // instruction budget ran out
+    Stopped(RunStatus<W>),
+    Fault(VmError),
+}
+
+// Runs the machine on the calling thread until it shuts down
+pub fn run<M: Memory>(
+    mem_handler: &mut MemHandler<M>,
+    input: &Receiver<M::Word>,
+    output: &Sender<M::Word>,
+) -> Shutdown<M::Word> {
+    loop {
+        match mem_handler.run_until_event() {
+            Ok(RunStatus::Output(val)) => {
+                let _ = output.send(val);
+            }
+            Ok(RunStatus::NeedsInput) => match input.recv() {
+                Ok(val) => mem_handler.add_input(val),
+                Err(_) => return Shutdown::InputClosed,
+            },
+            Ok(RunStatus::Halted) => return Shutdown::Halted,
+            Ok(status) => return Shutdown::Stopped(status),
+            Err(err) => return Shutdown::Fault(err),
+        }
+    }
+}
+
+// Runs the machine on a new thread, joining it gives back the machine and
+// why it stopped
+pub fn spawn<M>(
+    mut mem_handler: MemHandler<M>,
+    input: Receiver<M::Word>,
+    output: Sender<M::Word>,
+) -> JoinHandle<(MemHandler<M>, Shutdown<M::Word>)>
+where
+    M: Memory + Send + 'static,
+    M::Word: Send,
+{
+    thread::spawn(move || {
+        let shutdown = run(&mut mem_handler, &input, &output);
+        (mem_handler, shutdown)
+    })
+}
+
+#[test]
+fn test_feedback_loop() {
+    use crate::parser;
+    use std::sync::mpsc;
+
+    // Day 7 amplifiers in a feedback loop, one thread each
+    let program = parser::parse_str(
+        "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5",
+    );
+    let (senders, receivers): (Vec<_>, Vec<_>) = (0..5).map(|_| mpsc::channel()).unzip();
+    for (sender, phase) in senders.iter().zip([9, 8, 7, 6, 5].iter()) {
+        sender.send(*phase).unwrap();
+    }
+    senders[0].send(0).unwrap();
+
+    let mut outputs = senders.into_iter().cycle().skip(1);
+    let amplifiers: Vec<_> = receivers
+        .into_iter()
+        .map(|input| {
+            spawn(
+                MemHandler::new(program.clone()),
+                input,
+                outputs.next().unwrap(),
+            )
+        })
+        .collect();
+    drop(outputs);
+
+    let results: Vec<_> = amplifiers.into_iter().map(|a| a.join().unwrap()).collect();
+    assert!(results
+        .iter()
+        .all(|(_, shutdown)| *shutdown == Shutdown::Halted));
+    assert!(results[4].0.last_result() == Some(139629729));
+}
+
+#[test]
+fn test_shutdown() {
+    use std::sync::mpsc;
+
+    // Echoes its input until the input is closed
+    let (to_echo, input) = mpsc::channel();
+    let (output, from_echo) = mpsc::channel();
+    let echo = spawn(
+        MemHandler::new(vec![3, 7, 4, 7, 1105, 1, 0, 0]),
+        input,
+        output,
+    );
+
+    to_echo.send(1).unwrap();
+    to_echo.send(2).unwrap();
+    drop(to_echo);
+    assert!(from_echo.iter().collect::<Vec<i64>>() == vec![1, 2]);
+    assert!(echo.join().unwrap().1 == Shutdown::InputClosed);
+
+    let (_, input) = mpsc::channel();
+    let (output, _) = mpsc::channel();
+    let mut mem_handler = MemHandler::new(vec![104, 1, 99]);
+    assert!(run(&mut mem_handler, &input, &output) == Shutdown::Halted);
+    assert!(mem_handler.last_result() == Some(1));
+}
@@ -0,0 +1,278 @@
+// Step debugger driving a MemHandler one instruction at a time, see HELP
+// for the available commands.
+use crate::disasm;
+use crate::mem_handler::{MemHandler, RunStatus};
+use crate::watch::{Access, WatchAction};
+use std::collections::BTreeSet;
+
+pub const HELP: &str = "\
+s, step [n]         execute n (default 1) instructions
+c, continue         run until a breakpoint, missing input or halt
+b, break [addr]     set a breakpoint, or list them without an address
+d, delete <addr>    remove a breakpoint
+w, watch <a> [b]    pause when the program writes to memory a to b
+p, print <a> [b]    print memory from a to b (inclusive)
+l, list [n]         disassemble n (default 5) instructions from pc
+r, regs             show pc, relative base and pending input
+i, input <v>...     queue input values
+h, help             show this text
+q, quit             leave the debugger";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    Step(usize),
+    Continue,
+    Break(Option<usize>),
+    Delete(usize),
+    Watch(usize, usize),
+    Print(usize, usize),
+    List(usize),
+    Registers,
+    Input(Vec<i64>),
+    Help,
+    Quit,
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Result<Command, String> {
+        let mut words = line.split_whitespace();
+        let name = words.next().unwrap_or("");
+        let args: Vec<&str> = words.collect();
+
+        let number = |i: usize| -> Result<Option<usize>, String> {
+            match args.get(i) {
+                Some(arg) => arg
+                    .parse::<usize>()
+                    .map(Some)
+                    .map_err(|_| format!("bad address '{}'", arg)),
+                None => Ok(None),
+            }
+        };
+        let required = |i: usize| -> Result<usize, String> {
+            number(i)?.ok_or_else(|| format!("'{}' needs an address", name))
+        };
+        let range = || -> Result<(usize, usize), String> {
+            let from = required(0)?;
+            let to = number(1)?.unwrap_or(from);
+            if to < from {
+                return Err(format!("bad range {} to {}", from, to));
+            }
+            Ok((from, to))
+        };
+
+        match name {
+            "s" | "step" => Ok(Command::Step(number(0)?.unwrap_or(1))),
+            "c" | "continue" => Ok(Command::Continue),
+            "b" | "break" => Ok(Command::Break(number(0)?)),
+            "d" | "delete" => Ok(Command::Delete(required(0)?)),
+            "w" | "watch" => range().map(|(from, to)| Command::Watch(from, to)),
+            "p" | "print" => range().map(|(from, to)| Command::Print(from, to)),
+            "l" | "list" => Ok(Command::List(number(0)?.unwrap_or(5))),
+            "r" | "regs" => Ok(Command::Registers),
+            "i" | "input" => args
+                .iter()
+                .map(|a| a.parse::<i64>().map_err(|_| format!("bad input '{}'", a)))
+                .collect::<Result<Vec<i64>, String>>()
+                .map(Command::Input),
+            "h" | "help" => Ok(Command::Help),
+            "q" | "quit" => Ok(Command::Quit),
+            other => Err(format!("unknown command '{}', try 'help'", other)),
+        }
+    }
+}
+
+pub struct Debugger {
+    mem_handler: MemHandler,
+    breakpoints: BTreeSet<usize>,
+}
+
+impl Debugger {
+    pub fn new(mem_handler: MemHandler) -> Self {
+        Debugger {
+            mem_handler,
+            breakpoints: BTreeSet::new(),
+        }
+    }
+
+    pub fn mem_handler(&self) -> &MemHandler {
+        &self.mem_handler
+    }
+
+    // Runs the command and returns the text to show the user
+    pub fn execute(&mut self, command: Command) -> String {
+        let mut out = Vec::new();
+
+        match command {
+            Command::Step(steps) => {
+                for _ in 0..steps {
+                    if !self.step(&mut out) {
+                        break;
+                    }
+                }
+                out.push(self.current_line());
+            }
+            Command::Continue => {
+                while self.step(&mut out) {
+                    let pc = self.mem_handler.pc();
+                    if self.breakpoints.contains(&pc) {
+                        out.push(format!("breakpoint at {}", pc));
+                        break;
+                    }
+                }
+                out.push(self.current_line());
+            }
+            Command::Break(Some(address)) => {
+                self.breakpoints.insert(address);
+                out.push(format!("breakpoint set at {}", address));
+            }
+            Command::Break(None) => {
+                let addresses: Vec<String> =
+                    self.breakpoints.iter().map(|b| b.to_string()).collect();
+                out.push(format!("breakpoints: [{}]", addresses.join(", ")));
+            }
+            Command::Delete(address) => {
+                if self.breakpoints.remove(&address) {
+                    out.push(format!("breakpoint at {} removed", address));
+                } else {
+                    out.push(format!("no breakpoint at {}", address));
+                }
+            }
+            Command::Watch(from, to) => {
+                self.mem_handler
+                    .watch(from..=to, Access::Write, WatchAction::Pause);
+                out.push(format!("watching writes to {}..{}", from, to));
+            }
+            Command::Print(from, to) => {
+                for row in (from..=to).step_by(8) {
+                    let words: Vec<String> = (row..=to.min(row.saturating_add(7)))
+                        .map(|a| self.mem_handler.peek(a).to_string())
+                        .collect();
+                    out.push(format!("{:04}: {}", row, words.join(" ")));
+                }
+            }
+            Command::List(count) => {
+                let mut address = self.mem_handler.pc();
+                for _ in 0..count {
+                    let (line, length) = self.line_at(address);
+                    out.push(line);
+                    address = address.saturating_add(length);
+                }
+            }
+            Command::Registers => {
+                out.push(format!("pc: {}", self.mem_handler.pc()));
+                out.push(format!(
+                    "relative base: {}",
+                    self.mem_handler.relative_base()
+                ));
+                out.push(format!("input: {:?}", self.mem_handler.pending_input()));
+                out.push(format!("halted: {}", self.mem_handler.finished()));
+            }
+            Command::Input(values) => {
+                for value in values.iter() {
+                    self.mem_handler.add_input(*value);
+                }
+                out.push(format!("input: {:?}", self.mem_handler.pending_input()));
+            }
+            Command::Help => out.push(HELP.to_string()),
+            Command::Quit => {}
+        }
+
+        out.join("\n")
+    }
+
+    // Executes one instruction, returns false if execution can not continue
+    fn step(&mut self, out: &mut Vec<String>) -> bool {
+        match self.mem_handler.run() {
+            Ok(RunStatus::Stepped) => true,
+            Ok(RunStatus::Output(val)) => {
+                out.push(format!("output: {}", val));
+                true
+            }
+            Ok(RunStatus::Watch(hit)) => {
+                out.push(format!("watchpoint: {}", hit));
+                false
+            }
+            Ok(RunStatus::OutOfBudget) => {
+                out.push("out of instruction budget".to_string());
+                false
+            }
+            Ok(RunStatus::NoProgress) => {
+                out.push("stuck in a loop without progress".to_string());
+                false
+            }
+            Ok(RunStatus::NeedsInput) => {
+                out.push("waiting for input".to_string());
+                false
+            }
+            Ok(RunStatus::NeedsWiderWords) => {
+                out.push("overflow, needs wider words".to_string());
+                false
+            }
+            Ok(RunStatus::Halted) => {
+                out.push("halted".to_string());
+                false
+            }
+            Err(err) => {
+                out.push(format!("fault: {}", err));
+                false
+            }
+        }
+    }
+
+    fn current_line(&self) -> String {
+        self.line_at(self.mem_handler.pc()).0
+    }
+
+    // Disassembled line at address and the number of words it covers
+    fn line_at(&self, address: usize) -> (String, usize) {
+        match disasm::decode_at(self.mem_handler.memory(), address) {
+            Some(instruction) => (
+                format!("{:04}: {}", address, instruction),
+                instruction.params.len() + 1,
+            ),
+            None => (
+                format!("{:04}: DATA {}", address, self.mem_handler.peek(address)),
+                1,
+            ),
+        }
+    }
+}
+
+#[test]
+fn test_parse() {
+    assert!(Command::parse("s") == Ok(Command::Step(1)));
+    assert!(Command::parse("step 10") == Ok(Command::Step(10)));
+    assert!(Command::parse("b") == Ok(Command::Break(None)));
+    assert!(Command::parse("break 4") == Ok(Command::Break(Some(4))));
+    assert!(Command::parse("p 10") == Ok(Command::Print(10, 10)));
+    assert!(Command::parse("p 10 20") == Ok(Command::Print(10, 20)));
+    assert!(Command::parse("i 1 -2") == Ok(Command::Input(vec![1, -2])));
+    assert!(Command::parse("w 3 5") == Ok(Command::Watch(3, 5)));
+    assert!(Command::parse("p 10 5") == Err("bad range 10 to 5".to_string()));
+    assert!(Command::parse("w 5 3").is_err());
+    assert!(Command::parse("d").is_err());
+    assert!(Command::parse("b x").is_err());
+    assert!(Command::parse("jump").is_err());
+}
+
+#[test]
+fn test_debugger() {
+    // Reads a value and outputs it doubled, twice
+    let program = vec![3, 13, 1002, 13, 2, 13, 4, 13, 1105, 1, 0, 99, 0, 0];
+    let mut debugger = Debugger::new(MemHandler::new(program));
+
+    assert!(debugger.execute(Command::Step(1)) == "waiting for input\n0000: IN -> [13]");
+
+    debugger.execute(Command::Input(vec![4, 5]));
+    debugger.execute(Command::Break(Some(6)));
+    assert!(debugger.execute(Command::Continue) == "breakpoint at 6\n0006: OUT [13]");
+    assert!(debugger.execute(Command::Print(12, 13)) == "0012: 0 8");
+    let last = usize::MAX;
+    assert!(debugger.execute(Command::Print(last, last)) == format!("{}: 0", last));
+    assert!(debugger.execute(Command::Registers).contains("input: [5]"));
+
+    debugger.execute(Command::Delete(6));
+    debugger.execute(Command::Watch(13, 13));
+    let out = debugger.execute(Command::Continue);
+    assert!(out == "output: 8\nwatchpoint: write of 5 to 13 by instruction at 0\n0002: MUL [13], #2 -> [13]");
+}
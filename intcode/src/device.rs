@@ -0,0 +1,375 @@
+// Devices a program talks to through its input and output instructions, so
+// a puzzle driver only has to pick devices instead of writing its own loop
+// around run_until_event:
+//
+//     let device = device::attach(&mut mem_handler, Recorder::new(FixedInput::new(vec![1])));
+//     mem_handler.run_to_halt()?;
+//     let outputs = device.lock().unwrap().outputs();
+//
+// Ready-made devices:
+//
+//     FixedInput   hands out a list of values, ignores output
+//     Console      reads numbers from stdin and prints output to stdout
+//     Recorder     wraps another device and records everything going through
+//     Script       answers prompts with canned responses
+//     Screen       draws (x, y, tile) triples on a grid
+//
+// A device is attached as both the input source and the output sink of the
+// MemHandler, see set_input_source, so output goes to the device only and is
+// no longer kept in the result.
+use crate::mem_handler::MemHandler;
+use crate::memory::Memory;
+use crate::word::Word;
+use std::collections::{HashMap, VecDeque};
+use std::convert::TryFrom;
+use std::io::{self, BufRead, BufReader, Write};
+use std::sync::{Arc, Mutex};
+
+pub trait Device<W = i64> {
+    // Value for an input instruction once the input queue is empty, None if
+    // there is none right now. The program then waits with
+    // RunStatus::NeedsInput.
+    fn input(&mut self) -> Option<W> {
+        None
+    }
+
+    // Value produced by an output instruction
+    fn output(&mut self, val: W) {
+        let _ = val;
+    }
+}
+
+// Connects the input and output instructions of the program to device.
+// The returned handle gives access to the device between runs, holding its
+// lock while the program runs blocks the program.
+pub fn attach<M, D>(mem_handler: &mut MemHandler<M>, device: D) -> Arc<Mutex<D>>
+where
+    M: Memory,
+    D: Device<M::Word> + Send + 'static,
+{
+    let device = Arc::new(Mutex::new(device));
+    let source = Arc::clone(&device);
+    mem_handler.set_input_source(move || source.lock().ok()?.input());
+    let sink = Arc::clone(&device);
+    mem_handler.set_output_sink(move |val| {
+        if let Ok(mut sink) = sink.lock() {
+            sink.output(val);
+        }
+    });
+    device
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FixedInput<W = i64> {
+    values: VecDeque<W>,
+}
+
+impl<W> FixedInput<W> {
+    pub fn new(values: Vec<W>) -> Self {
+        FixedInput {
+            values: values.into(),
+        }
+    }
+}
+
+impl<W> Device<W> for FixedInput<W> {
+    fn input(&mut self) -> Option<W> {
+        self.values.pop_front()
+    }
+}
+
+// Reads one number per line as input, lines that are not a number are
+// skipped, and writes every output on a line of its own
+pub struct Console<R = BufReader<io::Stdin>, O = io::Stdout> {
+    input: R,
+    output: O,
+}
+
+impl Console {
+    pub fn stdio() -> Self {
+        Console::new(BufReader::new(io::stdin()), io::stdout())
+    }
+}
+
+impl<R: BufRead, O: Write> Console<R, O> {
+    pub fn new(input: R, output: O) -> Self {
+        Console { input, output }
+    }
+
+    // Where output is written
+    pub fn writer(&self) -> &O {
+        &self.output
+    }
+}
+
+impl<W: Word, R: BufRead, O: Write> Device<W> for Console<R, O> {
+    fn input(&mut self) -> Option<W> {
+        self.output.flush().ok()?;
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if self.input.read_line(&mut line).ok()? == 0 {
+                return None;
+            }
+            if let Ok(val) = line.trim().parse() {
+                return Some(val);
+            }
+        }
+    }
+
+    fn output(&mut self, val: W) {
+        let _ = writeln!(self.output, "{}", val);
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event<W = i64> {
+    Input(W),
+    Output(W),
+}
+
+// Passes everything on to the device it wraps and keeps a log of it
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Recorder<D, W = i64> {
+    device: D,
+    log: Vec<Event<W>>,
+}
+
+impl<D, W: Clone> Recorder<D, W> {
+    pub fn new(device: D) -> Self {
+        Recorder {
+            device,
+            log: vec![],
+        }
+    }
+
+    pub fn device(&self) -> &D {
+        &self.device
+    }
+
+    // Every input and output, in the order they happened
+    pub fn log(&self) -> &[Event<W>] {
+        &self.log
+    }
+
+    pub fn inputs(&self) -> Vec<W> {
+        self.log
+            .iter()
+            .filter_map(|event| match event {
+                Event::Input(val) => Some(val.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn outputs(&self) -> Vec<W> {
+        self.log
+            .iter()
+            .filter_map(|event| match event {
+                Event::Output(val) => Some(val.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl<D: Device<W>, W: Clone> Device<W> for Recorder<D, W> {
+    fn input(&mut self) -> Option<W> {
+        let val = self.device.input()?;
+        self.log.push(Event::Input(val.clone()));
+        Some(val)
+    }
+
+    fn output(&mut self, val: W) {
+        self.log.push(Event::Output(val.clone()));
+        self.device.output(val);
+    }
+}
+
+// Answers prompts in order. Once the output since the last answer ends with
+// the next prompt, its response is handed out as input. Input asked for
+// before that is not there.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Script<W = i64> {
+    steps: VecDeque<(Vec<W>, Vec<W>)>,
+    heard: Vec<W>,
+    response: VecDeque<W>,
+}
+
+impl<W: Word> Script<W> {
+    pub fn new() -> Self {
+        Script {
+            steps: VecDeque::new(),
+            heard: vec![],
+            response: VecDeque::new(),
+        }
+    }
+
+    // An empty prompt is answered right away
+    pub fn on(&mut self, prompt: Vec<W>, response: Vec<W>) {
+        self.steps.push_back((prompt, response));
+    }
+
+    // The same for programs talking ASCII, see ascii
+    pub fn on_str(&mut self, prompt: &str, response: &str) {
+        let codes = |text: &str| text.bytes().map(|b| W::from(b as i64)).collect();
+        self.on(codes(prompt), codes(response));
+    }
+
+    // Number of prompts not answered yet
+    pub fn remaining(&self) -> usize {
+        self.steps.len()
+    }
+}
+
+impl<W: Word> Device<W> for Script<W> {
+    fn input(&mut self) -> Option<W> {
+        if self.response.is_empty() {
+            let (prompt, _) = self.steps.front()?;
+            if !self.heard.ends_with(prompt) {
+                return None;
+            }
+            let (_, response) = self.steps.pop_front()?;
+            self.response = response.into();
+            self.heard.clear();
+        }
+        self.response.pop_front()
+    }
+
+    fn output(&mut self, val: W) {
+        self.heard.push(val);
+    }
+}
+
+// Grid drawn by the program one (x, y, tile) triple of outputs at a time,
+// a later tile at the same position replaces the earlier one
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Screen {
+    tiles: HashMap<(i64, i64), i64>,
+    pending: Vec<i64>,
+}
+
+impl Screen {
+    pub fn new() -> Self {
+        Screen::default()
+    }
+
+    pub fn tile(&self, x: i64, y: i64) -> Option<i64> {
+        self.tiles.get(&(x, y)).cloned()
+    }
+
+    pub fn tiles(&self) -> &HashMap<(i64, i64), i64> {
+        &self.tiles
+    }
+
+    // Number of positions showing tile
+    pub fn count(&self, tile: i64) -> usize {
+        self.tiles.values().filter(|t| **t == tile).count()
+    }
+
+    // The grid from its smallest to its largest position, tile n drawn as
+    // the n:th character of palette, unknown tiles as '?' and positions
+    // never drawn as spaces
+    pub fn render(&self, palette: &str) -> String {
+        let palette: Vec<char> = palette.chars().collect();
+        let xs = self.tiles.keys().map(|(x, _)| *x);
+        let ys = self.tiles.keys().map(|(_, y)| *y);
+        let (x_min, x_max) = (xs.clone().min().unwrap_or(0), xs.max().unwrap_or(-1));
+        let (y_min, y_max) = (ys.clone().min().unwrap_or(0), ys.max().unwrap_or(-1));
+
+        let mut out = String::new();
+        for y in y_min..=y_max {
+            for x in x_min..=x_max {
+                out.push(match self.tile(x, y) {
+                    Some(tile) => usize::try_from(tile)
+                        .ok()
+                        .and_then(|t| palette.get(t))
+                        .cloned()
+                        .unwrap_or('?'),
+                    None => ' ',
+                });
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+impl Device for Screen {
+    fn output(&mut self, val: i64) {
+        self.pending.push(val);
+        if let [x, y, tile] = self.pending[..] {
+            self.tiles.insert((x, y), tile);
+            self.pending.clear();
+        }
+    }
+}
+
+#[test]
+fn test_fixed_input_and_recorder() {
+    use crate::mem_handler::RunStatus;
+
+    // Outputs input + 1 for every input
+    let program = vec![3, 11, 101, 1, 11, 11, 4, 11, 1105, 1, 0, 0];
+    let mut mem_handler = MemHandler::new(program);
+    let device = attach(&mut mem_handler, Recorder::new(FixedInput::new(vec![1, 5])));
+    mem_handler.add_input(3);
+    assert!(mem_handler.run_to_halt() == Ok(RunStatus::NeedsInput));
+    assert!(mem_handler.get_result().is_none());
+
+    // Queued input is read before the device is asked
+    let device = device.lock().unwrap();
+    assert!(device.inputs() == vec![1, 5] && device.outputs() == vec![4, 2, 6]);
+    assert!(device.log()[..3] == [Event::Output(4), Event::Input(1), Event::Output(2)]);
+}
+
+#[test]
+fn test_console() {
+    use crate::mem_handler::RunStatus;
+
+    // Outputs the sum of its two inputs
+    let program = vec![3, 11, 3, 12, 1, 11, 12, 13, 4, 13, 99, 0, 0, 0];
+    let mut mem_handler = MemHandler::new(program);
+    let console = attach(&mut mem_handler, Console::new(&b"40\nfoo\n2\n"[..], vec![]));
+    assert!(mem_handler.run_to_halt() == Ok(RunStatus::Halted));
+    assert!(console.lock().unwrap().writer() == b"42\n");
+}
+
+#[test]
+fn test_script() {
+    use crate::ascii;
+    use crate::mem_handler::RunStatus;
+
+    // Prints "?" and echoes a line, twice
+    let program = vec![
+        104, 63, 3, 100, 4, 100, 1008, 100, 10, 101, 1006, 101, 2, 1001, 102, 1, 102, 1008, 102, 2,
+        101, 1006, 101, 0, 99,
+    ];
+    let mut mem_handler = MemHandler::new(program);
+    let mut script = Script::new();
+    script.on_str("?", "north\n");
+    script.on_str("north\n?", "take lamp\n");
+    let device = attach(&mut mem_handler, Recorder::new(script));
+    assert!(mem_handler.run_to_halt() == Ok(RunStatus::Halted));
+    let device = device.lock().unwrap();
+    assert!(device.device().remaining() == 0);
+    assert!(ascii::split_output(&device.outputs()).text == "?north\n?take lamp\n");
+
+    // Waits for a prompt that never comes
+    let mut mem_handler = MemHandler::new(vec![3, 0, 99]);
+    let mut script = Script::new();
+    script.on_str("?", "y\n");
+    attach(&mut mem_handler, script);
+    assert!(mem_handler.run_to_halt() == Ok(RunStatus::NeedsInput));
+}
+
+#[test]
+fn test_screen() {
+    let mut screen = Screen::new();
+    for val in [0, 0, 1, 2, 0, 1, 1, 1, 4, 2, 0, 2, 1, 1, 2, 3] {
+        screen.output(val);
+    }
+    assert!(screen.tile(2, 0) == Some(2) && screen.tile(5, 5).is_none());
+    assert!(screen.count(1) == 1 && screen.count(2) == 2);
+    assert!(screen.render(" #x") == "# x\n x \n");
+}
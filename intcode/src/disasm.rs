@@ -0,0 +1,165 @@
+// Disassembler, turns a program into a readable listing such as:
+//
+//     0000: ADD [r+3], #5 -> [100]           ; 1201,3,5,100
+//     0004: MUL [4], #3 -> [4]               ; 1002,4,3,4
+//     0008: HLT                              ; 99
+//     0009: DATA 33                          ; 33
+//
+// Parameters are written as [pos] in position mode, #imm in immediate mode
+// and [r+n] in relative mode. Words that do not decode into an instruction,
+// or would run past the end of the program, are listed as DATA. Since the
+// sweep is linear, data placed directly after code may be listed as
+// instructions.
+use crate::op_code::{decode, OpCode, ParamMode};
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Param {
+    pub mode: ParamMode,
+    pub value: i64,
+}
+
+impl fmt::Display for Param {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.mode {
+            ParamMode::Position => write!(f, "[{}]", self.value),
+            ParamMode::Immediate => write!(f, "#{}", self.value),
+            ParamMode::Relative if self.value < 0 => write!(f, "[r{}]", self.value),
+            ParamMode::Relative => write!(f, "[r+{}]", self.value),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Instruction {
+    pub op_code: OpCode,
+    pub params: Vec<Param>,
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.op_code.mnemonic())?;
+
+        let (sources, target) = if self.op_code.writes() {
+            let (target, sources) = self.params.split_last().unwrap();
+            (sources, Some(target))
+        } else {
+            (&self.params[..], None)
+        };
+
+        for (i, param) in sources.iter().enumerate() {
+            let separator = if i == 0 { " " } else { ", " };
+            write!(f, "{}{}", separator, param)?;
+        }
+        if let Some(target) = target {
+            write!(f, " -> {}", target)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Line {
+    pub address: usize,
+    pub words: Vec<i64>,
+    // None if the words are data
+    pub instruction: Option<Instruction>,
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = match &self.instruction {
+            Some(instruction) => instruction.to_string(),
+            None => format!("DATA {}", self.words[0]),
+        };
+        let words: Vec<String> = self.words.iter().map(|w| w.to_string()).collect();
+
+        write!(f, "{:04}: {:<32} ; {}", self.address, text, words.join(","))
+    }
+}
+
+// Decodes the instruction at address, if there is one
+pub fn decode_at(program: &[i64], address: usize) -> Option<Instruction> {
+    let (op_code, modes) = decode(address, *program.get(address)?).ok()?;
+    let nr_of_params = op_code.nr_of_params();
+
+    if address + nr_of_params >= program.len() {
+        return None;
+    }
+
+    let params = (0..nr_of_params)
+        .map(|i| Param {
+            mode: modes[i],
+            value: program[address + 1 + i],
+        })
+        .collect();
+
+    Some(Instruction { op_code, params })
+}
+
+pub fn disassemble(program: &[i64]) -> Vec<Line> {
+    let mut lines = Vec::new();
+    let mut address = 0;
+
+    while address < program.len() {
+        let instruction = decode_at(program, address);
+        let length = match &instruction {
+            Some(instruction) => instruction.params.len() + 1,
+            None => 1,
+        };
+
+        lines.push(Line {
+            address,
+            words: program[address..address + length].to_vec(),
+            instruction,
+        });
+        address += length;
+    }
+
+    lines
+}
+
+pub fn listing(program: &[i64]) -> String {
+    disassemble(program)
+        .iter()
+        .map(|line| format!("{}\n", line))
+        .collect()
+}
+
+#[test]
+fn test_disassemble() {
+    let lines = disassemble(&[1002, 4, 3, 4, 33]);
+    assert!(lines.len() == 2);
+    assert!(lines[0].instruction.as_ref().unwrap().to_string() == "MUL [4], #3 -> [4]");
+    assert!(lines[1].instruction.is_none());
+    assert!(lines[1].address == 4);
+
+    let lines = disassemble(&[1201, 3, 5, 100, 109, -1, 204, -2, 3, 7, 1105, 1, 0, 99]);
+    let text: Vec<String> = lines
+        .iter()
+        .map(|l| l.instruction.as_ref().unwrap().to_string())
+        .collect();
+    assert!(
+        text == vec![
+            "ADD [r+3], #5 -> [100]",
+            "ARB #-1",
+            "OUT [r-2]",
+            "IN -> [7]",
+            "JT #1, #0",
+            "HLT"
+        ]
+    );
+}
+
+#[test]
+fn test_listing() {
+    let text = listing(&[104, 7, 99, 42, 1]);
+    let lines: Vec<&str> = text.lines().collect();
+    assert!(lines[0].starts_with("0000: OUT #7 "));
+    assert!(lines[0].ends_with("; 104,7"));
+    assert!(lines[1].starts_with("0002: HLT "));
+    assert!(lines[2].starts_with("0003: DATA 42 "));
+    // Add at the end of the program without its parameters
+    assert!(lines[3].starts_with("0004: DATA 1 "));
+}
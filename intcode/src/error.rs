@@ -0,0 +1,45 @@
+use std::error::Error;
+use std::fmt;
+
+// Faults raised while running a program. The address is always the address
+// of the instruction that caused the fault.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VmError {
+    UnknownOpcode { address: usize, op_code: i64 },
+    BadParamMode { address: usize, mode: i64 },
+    NegativeAddress { address: usize, position: i64 },
+    WriteToImmediate { address: usize },
+    OutOfMemory { address: usize, position: usize },
+    Overflow { address: usize },
+}
+
+impl fmt::Display for VmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VmError::UnknownOpcode { address, op_code } => {
+                write!(f, "unknown op code {} at address {}", op_code, address)
+            }
+            VmError::BadParamMode { address, mode } => {
+                write!(f, "bad parameter mode {} at address {}", mode, address)
+            }
+            VmError::NegativeAddress { address, position } => write!(
+                f,
+                "access to negative position {} at address {}",
+                position, address
+            ),
+            VmError::WriteToImmediate { address } => {
+                write!(f, "write to immediate parameter at address {}", address)
+            }
+            VmError::OutOfMemory { address, position } => write!(
+                f,
+                "position {} is beyond the memory limit at address {}",
+                position, address
+            ),
+            VmError::Overflow { address } => {
+                write!(f, "arithmetic overflow at address {}", address)
+            }
+        }
+    }
+}
+
+impl Error for VmError {}
@@ -0,0 +1,53 @@
+// Where input comes from and output goes to, for programs that are driven
+// by something else than add_input and get_result. An input source is asked
+// for a value whenever the program reads input and the input queue is empty,
+// an output sink gets every value as it is produced instead of it being kept
+// in the result:
+//
+//     let mut lines = stdin.lock().lines();
+//     mem_handler.set_input_source(move || lines.next()?.ok()?.parse().ok());
+//     mem_handler.set_output_sink(|val| println!("{}", val));
+//
+// Any closure of the right type is a source or sink, iter_source turns an
+// iterator into a source.
+
+pub trait InputSource<W> {
+    // None if there is no input right now, the program then waits with
+    // RunStatus::NeedsInput and asks again when run
+    fn next_input(&mut self) -> Option<W>;
+}
+
+impl<W, F: FnMut() -> Option<W>> InputSource<W> for F {
+    fn next_input(&mut self) -> Option<W> {
+        self()
+    }
+}
+
+pub trait OutputSink<W> {
+    fn output(&mut self, val: W);
+}
+
+impl<W, F: FnMut(W)> OutputSink<W> for F {
+    fn output(&mut self, val: W) {
+        self(val)
+    }
+}
+
+// Source handing out the items of an iterator
+pub fn iter_source<W, I: Iterator<Item = W>>(mut iter: I) -> impl InputSource<W> {
+    move || iter.next()
+}
+
+#[test]
+fn test_sources_and_sinks() {
+    let mut source = iter_source(vec![1, 2].into_iter());
+    assert!(source.next_input() == Some(1));
+    assert!(source.next_input() == Some(2));
+    assert!(source.next_input().is_none());
+
+    let mut seen = vec![];
+    let mut sink = |val: i64| seen.push(val);
+    sink.output(3);
+    sink.output(4);
+    assert!(seen == vec![3, 4]);
+}
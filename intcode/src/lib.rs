@@ -0,0 +1,116 @@
+// Shared intcode computer used by day 2, 5, 7 and 9.
+//
+// Links to puzzles:
+//     https://adventofcode.com/2019/day/2
+//     https://adventofcode.com/2019/day/5
+//     https://adventofcode.com/2019/day/9
+//
+// Summary:
+//
+// OPCODES:
+//  1:  Add instruction, followed by two parameters and lastly the address to
+//      where the results should be stored.
+//  2:  Multiply instruction, followed by two parameters and lastly the
+//      address where the results should be stored.
+//  3:  Takes a single integer as input and saves it to the address pointed
+//      to by its only parameter.
+//  4:  Outputs the value of its only parameter, for example instruction
+//      '4, 50' would output the value at adress 50.
+//  5:  Jump if true, if the first parameter is non-zero the instruction
+//      pointer is set to the value of the second parameter.
+//  6:  Jump if false, if the first parameter is zero the instruction pointer
+//      is set to the value of the second parameter.
+//  7:  Less than, stores 1 at the address of the third parameter if the first
+//      parameter is less than the second, otherwise 0.
+//  8:  Equals, stores 1 at the address of the third parameter if the first
+//      parameter equals the second, otherwise 0.
+//  9:  Adjusts the relative base by the value of its only parameter.
+//  99: Halt.
+//
+// PARAMETER MODES:
+//  0 -> POSITION_MODE: parameter will be interpreted as a position, for
+//     example:
+//         parameter 50, the value is stored at adress 50 in memory.
+//  1 -> IMMEDIATE_MODE: The parameter value is simply the value that will
+//         be interpreted.
+//  2 -> RELATIVE_MODE: Like position mode, but the position is offset by the
+//         current relative base.
+//
+// INSTSTRUCTIONS FORMAT:
+//    Parameter mode is stored in the same value as the instruction's
+//    opcode.
+//
+//    OPCODE: The opcode is a two-digit number based only on the ones and
+//    tens digits of the value.
+//
+// PARAMETERS:
+//     Parameter modes are single digits, one per parameter, read right-
+//     to-left from the opcode: the first parameter's mode is in the hundreds
+//     digit, the second parameter's mode is in the thousands digit, the third
+//     parameter's mode is in the ten-thousands digit, and so on.
+//     Missing modes are 0.
+//
+// EXAMPLE PROGRAM: '1002,4,3,4,33'
+//     1002 gives the following instructions:
+//         02 -> opcode 2 which is multiply instruction
+//         0  -> position mode for parameter 1
+//         1  -> immediate mode for parameter 2
+//
+//     This will result in position mode for first parameter, 4. Which points
+//     to the value 33. The second parameter is in immediate mode which
+//     gives the second parameter a value of 3.
+//
+//     The last value 4 is where we store the result (33*3=99), which is in
+//     position 4. Executing the program will result as following:
+//
+//         '1002, 4, 3, 4, 99'
+//
+// MEMORY:
+//     Memory beyond the initial program is available and starts out as 0.
+//     It is allocated on first write, either as one growing vector
+//     (DenseMemory, the default) or in pages of PAGE_SIZE words
+//     (PagedMemory) for programs that write to far away addresses.
+//
+// WORDS:
+//     Memory holds i64 words by default. The computer is generic over its
+//     word type (see Word), i128 and BigInt words run programs whose numbers
+//     do not fit in an i64. With Arithmetic::Widening a program starts out
+//     with i64 words and moves on to i128 ones once it needs them.
+//
+// I/O:
+//     Input is queued with add_input and output collected in the result.
+//     Programs that talk back and forth can be run against a Device instead,
+//     see device.
+
+mod arithmetic;
+pub mod ascii;
+pub mod asm;
+pub mod channel;
+pub mod debugger;
+pub mod device;
+pub mod disasm;
+mod error;
+mod io;
+mod mem_handler;
+mod memory;
+pub mod network;
+mod op_code;
+pub mod parser;
+pub mod profile;
+pub mod search;
+mod snapshot;
+pub mod symbolic;
+pub mod trace;
+pub mod transpile;
+mod watch;
+mod word;
+
+pub use arithmetic::Arithmetic;
+pub use error::VmError;
+pub use io::{iter_source, InputSource, OutputSink};
+pub use mem_handler::{MemHandler, RunStatus, DEFAULT_MEMORY_LIMIT};
+pub use memory::{DenseMemory, Memory, PagedMemory, PAGE_SIZE};
+pub use op_code::{decode, OpCode, ParamMode};
+pub use snapshot::Snapshot;
+pub use watch::{Access, WatchAction, WatchHit};
+pub use word::Word;
@@ -0,0 +1,1098 @@
+use crate::arithmetic::Arithmetic;
+use crate::error::VmError;
+use crate::io::{InputSource, OutputSink};
+use crate::memory::{DenseMemory, Memory};
+use crate::op_code::{decode, OpCode, ParamMode};
+use crate::profile::Profile;
+use crate::snapshot::Snapshot;
+use crate::trace::TraceEntry;
+use crate::watch::{Access, WatchAction, WatchHit, Watchpoints};
+use crate::word::Word;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::ops::RangeInclusive;
+
+// Programs are not allowed to grow their memory beyond this many words
+// unless the limit is raised with set_memory_limit.
+pub const DEFAULT_MEMORY_LIMIT: usize = 1 << 24;
+
+// What happened when the computer was asked to run, W is the word type of
+// the computer
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RunStatus<W = i64> {
+    // An output instruction produced a value
+    Output(W),
+    // An input instruction found the input queue empty. The instruction
+    // pointer is left on the instruction so it is retried once input has
+    // been added.
+    NeedsInput,
+    // The program has reached its exit instruction
+    Halted,
+    // Any other instruction was executed
+    Stepped,
+    // The instruction accessed memory covered by a pausing watchpoint. It
+    // has been executed completely. Outputs are reported first, the hit is
+    // then returned by the next call to run.
+    Watch(WatchHit<W>),
+    // The instruction budget is used up, nothing was executed. Running
+    // continues once the budget is raised.
+    OutOfBudget,
+    // The computer is back in a state it has been in before without writing
+    // to memory, moving the relative base or doing any I/O in between, so it
+    // will loop forever
+    NoProgress,
+    // An add or multiply overflowed with Arithmetic::Widening, nothing was
+    // executed. See widen.
+    NeedsWiderWords,
+}
+
+// An op code with the modes of its parameters
+type Decoded = (OpCode, [ParamMode; 3]);
+
+// Addresses below this are cached in a vector, code beyond it, e.g. far out
+// in PagedMemory, in a map so it does not need an entry for every address
+// before it
+const NEAR_CODE: usize = 1 << 16;
+
+#[derive(Default)]
+struct DecodeCache {
+    near: Vec<Option<Decoded>>,
+    far: HashMap<usize, Decoded>,
+}
+
+impl DecodeCache {
+    #[inline]
+    fn get(&self, address: usize) -> Option<Decoded> {
+        if address < NEAR_CODE {
+            self.near.get(address).cloned().flatten()
+        } else {
+            self.far.get(&address).cloned()
+        }
+    }
+
+    fn insert(&mut self, address: usize, decoded: Decoded) {
+        if address < NEAR_CODE {
+            if self.near.len() <= address {
+                self.near.resize(address + 1, None);
+            }
+            self.near[address] = Some(decoded);
+        } else {
+            self.far.insert(address, decoded);
+        }
+    }
+
+    #[inline]
+    fn remove(&mut self, address: usize) {
+        if address < NEAR_CODE {
+            if let Some(cached) = self.near.get_mut(address) {
+                *cached = None;
+            }
+        } else {
+            self.far.remove(&address);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.near.clear();
+        self.far.clear();
+    }
+}
+
+type Source<W> = Box<dyn InputSource<W> + Send>;
+type Sink<W> = Box<dyn OutputSink<W> + Send>;
+
+// The memory backend defaults to a plain vector, which is the fastest as long
+// as the program stays close to its own code. Programs writing to far away
+// addresses can use PagedMemory instead. The word type is the one of the
+// memory, e.g. MemHandler<DenseMemory<BigInt>> computes exactly.
+pub struct MemHandler<M: Memory = DenseMemory> {
+    memory: M,
+    op: usize,
+    relative_base: i64,
+    result: Vec<M::Word>,
+    input: VecDeque<M::Word>,
+    // Asked for input once the queue is empty, when set
+    source: Option<Source<M::Word>>,
+    // Gets the output instead of result, when set
+    sink: Option<Sink<M::Word>>,
+    finished: bool,
+    memory_limit: usize,
+    arithmetic: Arithmetic,
+    trace: Option<Vec<TraceEntry<M::Word>>>,
+    watchpoints: Watchpoints<M::Word>,
+    profile: Option<Profile>,
+    executed: u64,
+    budget: Option<u64>,
+    // Instructions executed since the last progress, when enabled. Memory
+    // and the relative base have not changed since, so a pc seen again
+    // means the program is in a loop.
+    seen: Option<HashSet<usize>>,
+    progressed: bool,
+    // Decoded instruction per address, when enabled
+    decoded: Option<DecodeCache>,
+    paused: Option<WatchHit<M::Word>>,
+}
+
+impl MemHandler {
+    pub fn new(raw: Vec<i64>) -> Self {
+        MemHandler::with_words(raw)
+    }
+
+    // The same machine with i128 words, continuing where this one stopped,
+    // e.g. with RunStatus::NeedsWiderWords. The number of instructions
+    // executed, memory limit, arithmetic, instruction budget, no progress
+    // detection and decode cache carry over, where i128 words overflow like
+    // Arithmetic::Checked with widening arithmetic. Input sources, output
+    // sinks, watchpoints, traces and profiles do not.
+    pub fn widen(self) -> MemHandler<DenseMemory<i128>> {
+        let wide = |words: &[i64]| words.iter().map(|word| *word as i128).collect::<Vec<_>>();
+        let mut mem_handler = MemHandler::with_words(wide(self.memory()));
+        mem_handler.op = self.op;
+        mem_handler.relative_base = self.relative_base;
+        mem_handler.input = self.input.iter().map(|word| *word as i128).collect();
+        mem_handler.result = wide(&self.result);
+        mem_handler.finished = self.finished;
+        mem_handler.memory_limit = self.memory_limit;
+        mem_handler.arithmetic = self.arithmetic;
+        mem_handler.executed = self.executed;
+        mem_handler.budget = self.budget;
+        if self.seen.is_some() {
+            mem_handler.detect_no_progress();
+        }
+        if self.decoded.is_some() {
+            mem_handler.enable_decode_cache();
+        }
+        mem_handler
+    }
+}
+
+impl<W: Word> MemHandler<DenseMemory<W>> {
+    // Program with words of any type, see parser::parse_str_as
+    pub fn with_words(raw: Vec<W>) -> Self {
+        MemHandler::with_memory(DenseMemory::from(raw))
+    }
+
+    // The whole memory, including anything allocated after the program
+    pub fn memory(&self) -> &[W] {
+        self.memory.as_slice()
+    }
+}
+
+impl<M: Memory> MemHandler<M> {
+    pub fn with_memory(memory: M) -> Self {
+        MemHandler {
+            memory,
+            op: 0,
+            relative_base: 0,
+            result: vec![],
+            input: VecDeque::new(),
+            source: None,
+            sink: None,
+            finished: false,
+            memory_limit: DEFAULT_MEMORY_LIMIT,
+            arithmetic: Arithmetic::default(),
+            trace: None,
+            watchpoints: Watchpoints::default(),
+            profile: None,
+            executed: 0,
+            budget: None,
+            seen: None,
+            progressed: false,
+            decoded: None,
+            paused: None,
+        }
+    }
+
+    fn next(&mut self) -> Result<Decoded, VmError> {
+        // Every write drops what was cached for its address, so a cached
+        // instruction is still the word in memory
+        if let Some(cached) = self.decoded.as_ref().and_then(|d| d.get(self.op)) {
+            return Ok(cached);
+        }
+
+        let instruction = match self.get(self.op).to_i64() {
+            Some(instruction) => instruction,
+            // Far too large for any op code, reported as the largest i64
+            None => {
+                return Err(VmError::UnknownOpcode {
+                    address: self.op,
+                    op_code: i64::MAX,
+                })
+            }
+        };
+        let next = decode(self.op, instruction)?;
+        if let Some(decoded) = &mut self.decoded {
+            decoded.insert(self.op, next);
+        }
+        Ok(next)
+    }
+
+    fn get(&self, address: usize) -> M::Word {
+        self.memory.get(address)
+    }
+
+    // Memory read by the program, as opposed to peek
+    fn read(&mut self, address: usize) -> M::Word {
+        let val = self.get(address);
+        if !self.watchpoints.is_empty() {
+            self.check_watch(address, Access::Read, val.clone());
+        }
+        val
+    }
+
+    fn set(&mut self, address: usize, val: M::Word) -> Result<(), VmError> {
+        if self.seen.is_some() && self.get(address) != val {
+            self.progressed = true;
+        }
+        self.write(address, val.clone())?;
+        if let Some(entry) = self.traced() {
+            entry.writes.push((address, val.clone()));
+        }
+        if !self.watchpoints.is_empty() {
+            self.check_watch(address, Access::Write, val);
+        }
+        Ok(())
+    }
+
+    fn check_watch(&mut self, address: usize, access: Access, value: M::Word) {
+        let hit = WatchHit {
+            pc: self.op,
+            address,
+            access,
+            value,
+        };
+        if self.watchpoints.check(&hit) && self.paused.is_none() {
+            self.paused = Some(hit);
+        }
+    }
+
+    fn move_op(&mut self, to: &M::Word) -> Result<(), VmError> {
+        self.op = self.position_of(to, 0)?;
+        Ok(())
+    }
+
+    fn move_op_by(&mut self, steps: usize) {
+        self.op += steps;
+    }
+
+    pub fn add_input(&mut self, input: M::Word) {
+        self.input.push_back(input);
+        self.forget_seen();
+    }
+
+    // Anything changed from outside may get a program out of its loop
+    fn forget_seen(&mut self) {
+        if let Some(seen) = &mut self.seen {
+            seen.clear();
+        }
+    }
+
+    // Reads input from source whenever the input queue is empty
+    pub fn set_input_source<S: InputSource<M::Word> + Send + 'static>(&mut self, source: S) {
+        self.source = Some(Box::new(source));
+    }
+
+    // Sends every output to sink as it is produced. It is then no longer
+    // kept, so get_result and last_result only see earlier output.
+    pub fn set_output_sink<S: OutputSink<M::Word> + Send + 'static>(&mut self, sink: S) {
+        self.sink = Some(Box::new(sink));
+    }
+
+    pub fn get_result(&self) -> Option<Vec<M::Word>> {
+        if self.result.is_empty() {
+            None
+        } else {
+            Some(self.result.clone())
+        }
+    }
+
+    pub fn last_result(&self) -> Option<M::Word> {
+        self.result.last().cloned()
+    }
+
+    // The output collected so far, which is then cleared
+    pub fn take_result(&mut self) -> Vec<M::Word> {
+        std::mem::take(&mut self.result)
+    }
+
+    pub fn finished(&self) -> bool {
+        self.finished
+    }
+
+    // Reads memory, memory that was never written reads as 0
+    pub fn peek(&self, address: usize) -> M::Word {
+        self.get(address)
+    }
+
+    // Writes memory from outside the program, e.g. to patch in arguments or
+    // to get a program that made no progress going again
+    pub fn poke(&mut self, address: usize, val: M::Word) -> Result<(), VmError> {
+        self.write(address, val)?;
+        self.forget_seen();
+        Ok(())
+    }
+
+    fn write(&mut self, address: usize, val: M::Word) -> Result<(), VmError> {
+        let allocated = self.memory.allocated_with(address);
+        if allocated.is_none_or(|words| words > self.memory_limit) {
+            return Err(VmError::OutOfMemory {
+                address: self.op,
+                position: address,
+            });
+        }
+
+        self.memory.set(address, val);
+        if let Some(decoded) = &mut self.decoded {
+            decoded.remove(address);
+        }
+        Ok(())
+    }
+
+    // Copy of the full state of the computer
+    pub fn snapshot(&self) -> Snapshot<M> {
+        Snapshot {
+            memory: self.memory.clone(),
+            pc: self.op,
+            relative_base: self.relative_base,
+            input: self.pending_input(),
+            output: self.result.clone(),
+            finished: self.finished,
+        }
+    }
+
+    // Puts the computer back in the state of the snapshot. Settings such as
+    // the memory limit and tracing are kept.
+    pub fn restore(&mut self, snapshot: &Snapshot<M>) {
+        self.memory.clone_from(&snapshot.memory);
+        self.op = snapshot.pc;
+        self.relative_base = snapshot.relative_base;
+        self.input = snapshot.input.iter().cloned().collect();
+        self.result.clone_from(&snapshot.output);
+        self.finished = snapshot.finished;
+        self.paused = None;
+        if let Some(decoded) = &mut self.decoded {
+            decoded.clear();
+        }
+        self.forget_seen();
+    }
+
+    pub fn from_snapshot(snapshot: &Snapshot<M>) -> Self {
+        let mut mem_handler = MemHandler::with_memory(snapshot.memory.clone());
+        mem_handler.restore(snapshot);
+        mem_handler
+    }
+
+    // Address of the next instruction
+    pub fn pc(&self) -> usize {
+        self.op
+    }
+
+    pub fn relative_base(&self) -> i64 {
+        self.relative_base
+    }
+
+    // Queued input, in the order it will be read. Input the source has not
+    // handed out yet is not included.
+    pub fn pending_input(&self) -> Vec<M::Word> {
+        self.input.iter().cloned().collect()
+    }
+
+    // Starts recording every executed instruction
+    pub fn enable_trace(&mut self) {
+        if self.trace.is_none() {
+            self.trace = Some(vec![]);
+        }
+    }
+
+    // Everything recorded since tracing was enabled or the trace was last
+    // taken
+    pub fn take_trace(&mut self) -> Vec<TraceEntry<M::Word>> {
+        match &mut self.trace {
+            Some(trace) => std::mem::take(trace),
+            None => vec![],
+        }
+    }
+
+    // Starts counting executed instructions, see Profile
+    pub fn enable_profile(&mut self) {
+        if self.profile.is_none() {
+            self.profile = Some(Profile::default());
+        }
+    }
+
+    // Profile collected so far, profiling stays enabled with a new profile
+    pub fn take_profile(&mut self) -> Option<Profile> {
+        self.profile.as_mut().map(std::mem::take)
+    }
+
+    fn traced(&mut self) -> Option<&mut TraceEntry<M::Word>> {
+        self.trace.as_mut().and_then(|trace| trace.last_mut())
+    }
+
+    // Watches program accesses to the addresses in range, returns an id for
+    // unwatch
+    pub fn watch(
+        &mut self,
+        range: RangeInclusive<usize>,
+        access: Access,
+        action: WatchAction<M::Word>,
+    ) -> usize {
+        self.watchpoints.add(range, access, action)
+    }
+
+    // Removes a watchpoint, returns false if there was none with that id
+    pub fn unwatch(&mut self, id: usize) -> bool {
+        self.watchpoints.remove(id)
+    }
+
+    // Keeps every instruction decoded after its first execution, instead of
+    // decoding it again every time. Writes to an address drop what was
+    // decoded there, so self-modifying programs keep working.
+    pub fn enable_decode_cache(&mut self) {
+        if self.decoded.is_none() {
+            self.decoded = Some(DecodeCache::default());
+        }
+    }
+
+    // Number of instructions executed so far
+    pub fn executed(&self) -> u64 {
+        self.executed
+    }
+
+    // Allows the computer to execute this many more instructions, after that
+    // run returns RunStatus::OutOfBudget
+    pub fn set_instruction_budget(&mut self, instructions: u64) {
+        self.budget = Some(instructions);
+    }
+
+    // Makes run return RunStatus::NoProgress when the program is stuck in a
+    // loop that does not change memory or the relative base and does no I/O
+    pub fn detect_no_progress(&mut self) {
+        if self.seen.is_none() {
+            self.seen = Some(HashSet::new());
+        }
+    }
+
+    // Maximum number of words the program may use
+    pub fn set_memory_limit(&mut self, words: usize) {
+        self.memory_limit = words;
+    }
+
+    // How add and multiply handle overflow, wrapping by default
+    pub fn set_arithmetic(&mut self, arithmetic: Arithmetic) {
+        self.arithmetic = arithmetic;
+    }
+
+    fn overflow<T>(&self, result: Option<T>) -> Result<T, VmError> {
+        result.ok_or(VmError::Overflow { address: self.op })
+    }
+
+    // Result of an add or multiply, None if the program has to continue
+    // with wider words
+    fn computed(&self, result: Option<M::Word>) -> Result<Option<M::Word>, VmError> {
+        match result {
+            None if self.arithmetic == Arithmetic::Widening && M::Word::CAN_WIDEN => Ok(None),
+            result => self.overflow(result).map(Some),
+        }
+    }
+
+    fn to_position(&self, position: i64) -> Result<usize, VmError> {
+        if position < 0 {
+            return Err(VmError::NegativeAddress {
+                address: self.op,
+                position,
+            });
+        }
+
+        Ok(position as usize)
+    }
+
+    // Position given by a word plus an offset. A word that does not fit in
+    // an i64 is always a negative position or one beyond any memory limit.
+    fn position_of(&self, word: &M::Word, offset: i64) -> Result<usize, VmError> {
+        match word.to_i64().and_then(|word| word.checked_add(offset)) {
+            Some(position) => self.to_position(position),
+            None if *word < M::Word::from(0) => Err(VmError::NegativeAddress {
+                address: self.op,
+                position: i64::MIN,
+            }),
+            None => Err(VmError::OutOfMemory {
+                address: self.op,
+                position: usize::MAX,
+            }),
+        }
+    }
+
+    fn get_next_position(&mut self, offset: usize, mode: ParamMode) -> Result<usize, VmError> {
+        let address = self.get(self.op + offset);
+
+        let position = match mode {
+            ParamMode::Position => self.position_of(&address, 0)?,
+            ParamMode::Relative => self.position_of(&address, self.relative_base)?,
+            ParamMode::Immediate => return Err(VmError::WriteToImmediate { address: self.op }),
+        };
+
+        if let Some(entry) = self.traced() {
+            entry.operands.push(M::Word::from(position as i64));
+        }
+        Ok(position)
+    }
+
+    //
+    //  POSITION_MODE:  0
+    //  IMMEDIATE_MODE: 1
+    //  RELATIVE_MODE:  2
+    //
+    fn get_next_parameter(&mut self, offset: usize, mode: ParamMode) -> Result<M::Word, VmError> {
+        let address = self.get(self.op + offset);
+
+        let value = match mode {
+            ParamMode::Position => {
+                let position = self.position_of(&address, 0)?;
+                self.read(position)
+            }
+            ParamMode::Relative => {
+                let position = self.position_of(&address, self.relative_base)?;
+                self.read(position)
+            }
+            ParamMode::Immediate => address,
+        };
+
+        if let Some(entry) = self.traced() {
+            entry.operands.push(value.clone());
+        }
+        Ok(value)
+    }
+
+    // Runs until something the caller may want to react to happens, i.e.
+    // an output, missing input or the program halting.
+    pub fn run_until_event(&mut self) -> Result<RunStatus<M::Word>, VmError> {
+        loop {
+            match self.run()? {
+                RunStatus::Stepped => continue,
+                status => return Ok(status),
+            }
+        }
+    }
+
+    // Runs until the program halts or waits for input, outputs are
+    // collected and available through get_result.
+    pub fn run_to_halt(&mut self) -> Result<RunStatus<M::Word>, VmError> {
+        loop {
+            match self.run_until_event()? {
+                RunStatus::Output(_) => continue,
+                status => return Ok(status),
+            }
+        }
+    }
+
+    // Executes a single instruction. On a fault the instruction pointer is
+    // left on the faulting instruction.
+    pub fn run(&mut self) -> Result<RunStatus<M::Word>, VmError> {
+        if let Some(hit) = self.paused.take() {
+            return Ok(RunStatus::Watch(hit));
+        }
+        if self.finished {
+            return Ok(RunStatus::Halted);
+        }
+        if self.budget == Some(0) {
+            return Ok(RunStatus::OutOfBudget);
+        }
+        if let Some(seen) = &self.seen {
+            if seen.contains(&self.op) {
+                return Ok(RunStatus::NoProgress);
+            }
+        }
+
+        let (op_code, modes) = self.next()?;
+        if let Some(trace) = &mut self.trace {
+            trace.push(TraceEntry::new(self.op, op_code));
+        }
+
+        let pc = self.op;
+        let relative_base = self.relative_base;
+        self.progressed = false;
+        let status = self.execute(op_code, modes);
+
+        let executed = matches!(
+            status,
+            Ok(ref status) if *status != RunStatus::NeedsInput && *status != RunStatus::NeedsWiderWords
+        );
+        if executed {
+            self.executed += 1;
+            if let Some(budget) = &mut self.budget {
+                *budget -= 1;
+            }
+            if let Some(profile) = &mut self.profile {
+                profile.record(pc, op_code, self.op);
+            }
+            if let Some(seen) = &mut self.seen {
+                let moved = self.relative_base != relative_base;
+                if self.progressed || moved || op_code == OpCode::Input || op_code == OpCode::Output
+                {
+                    seen.clear();
+                } else {
+                    seen.insert(pc);
+                }
+            }
+        } else if let Some(trace) = &mut self.trace {
+            // Nothing was executed, so there is nothing to record
+            trace.pop();
+        }
+
+        match status {
+            Ok(RunStatus::Stepped) => match self.paused.take() {
+                Some(hit) => Ok(RunStatus::Watch(hit)),
+                None => status,
+            },
+            Ok(RunStatus::Output(_)) | Ok(RunStatus::Halted) => status,
+            // The instruction did not run and will be retried
+            _ => {
+                self.paused = None;
+                status
+            }
+        }
+    }
+
+    fn execute(
+        &mut self,
+        op_code: OpCode,
+        modes: [ParamMode; 3],
+    ) -> Result<RunStatus<M::Word>, VmError> {
+        match op_code {
+            OpCode::Add => {
+                let arg0 = self.get_next_parameter(1, modes[0])?;
+                let arg1 = self.get_next_parameter(2, modes[1])?;
+                let arg2 = self.get_next_position(3, modes[2])?;
+                let val = match self.computed(arg0.add_with(&arg1, self.arithmetic))? {
+                    Some(val) => val,
+                    None => return Ok(RunStatus::NeedsWiderWords),
+                };
+                self.set(arg2, val)?;
+                self.move_op_by(4);
+            }
+            OpCode::Multiply => {
+                let arg0 = self.get_next_parameter(1, modes[0])?;
+                let arg1 = self.get_next_parameter(2, modes[1])?;
+                let arg2 = self.get_next_position(3, modes[2])?;
+                let val = match self.computed(arg0.multiply_with(&arg1, self.arithmetic))? {
+                    Some(val) => val,
+                    None => return Ok(RunStatus::NeedsWiderWords),
+                };
+                self.set(arg2, val)?;
+                self.move_op_by(4);
+            }
+            OpCode::Input => {
+                let arg0 = self.get_next_position(1, modes[0])?;
+                if self.input.is_empty() {
+                    let next = self.source.as_mut().and_then(|s| s.next_input());
+                    self.input.extend(next);
+                }
+                let val = match self.input.front() {
+                    Some(val) => val.clone(),
+                    None => return Ok(RunStatus::NeedsInput),
+                };
+                if let Some(entry) = self.traced() {
+                    entry.input = Some(val.clone());
+                }
+                // Only consumed once the write succeeded
+                self.set(arg0, val)?;
+                self.input.pop_front();
+                self.move_op_by(2);
+            }
+            OpCode::Output => {
+                let arg0 = self.get_next_parameter(1, modes[0])?;
+                if let Some(entry) = self.traced() {
+                    entry.output = Some(arg0.clone());
+                }
+                match &mut self.sink {
+                    Some(sink) => sink.output(arg0.clone()),
+                    None => self.result.push(arg0.clone()),
+                }
+                self.move_op_by(2);
+                return Ok(RunStatus::Output(arg0));
+            }
+            OpCode::JumpIfTrue => {
+                let arg0 = self.get_next_parameter(1, modes[0])?;
+                if arg0 != M::Word::from(0) {
+                    let arg1 = self.get_next_parameter(2, modes[1])?;
+                    self.move_op(&arg1)?;
+                } else {
+                    self.move_op_by(3);
+                }
+            }
+            OpCode::JumpIfFalse => {
+                let arg0 = self.get_next_parameter(1, modes[0])?;
+                if arg0 == M::Word::from(0) {
+                    let arg1 = self.get_next_parameter(2, modes[1])?;
+                    self.move_op(&arg1)?;
+                } else {
+                    self.move_op_by(3);
+                }
+            }
+            OpCode::LessThan => {
+                let arg0 = self.get_next_parameter(1, modes[0])?;
+                let arg1 = self.get_next_parameter(2, modes[1])?;
+                let arg2 = self.get_next_position(3, modes[2])?;
+                let val = M::Word::from(if arg0 < arg1 { 1 } else { 0 });
+                self.set(arg2, val)?;
+                self.move_op_by(4);
+            }
+            OpCode::Equal => {
+                let arg0 = self.get_next_parameter(1, modes[0])?;
+                let arg1 = self.get_next_parameter(2, modes[1])?;
+                let arg2 = self.get_next_position(3, modes[2])?;
+                let val = M::Word::from(if arg0 == arg1 { 1 } else { 0 });
+                self.set(arg2, val)?;
+                self.move_op_by(4);
+            }
+            OpCode::ChangeRelative => {
+                let arg0 = self.get_next_parameter(1, modes[0])?;
+                let offset = self.overflow(arg0.to_i64())?;
+                self.relative_base = self.overflow(self.relative_base.checked_add(offset))?;
+                let relative_base = self.relative_base;
+                if let Some(entry) = self.traced() {
+                    entry.relative_base = Some(relative_base);
+                }
+                self.move_op_by(2);
+            }
+            OpCode::Exit => {
+                self.finished = true;
+                return Ok(RunStatus::Halted);
+            }
+        }
+
+        Ok(RunStatus::Stepped)
+    }
+}
+
+#[cfg(test)]
+fn run_program(program: Vec<i64>, input: &[i64]) -> MemHandler {
+    let mut mem_handler = MemHandler::new(program);
+    for val in input {
+        mem_handler.add_input(*val);
+    }
+    assert!(mem_handler.run_to_halt() == Ok(RunStatus::Halted));
+    mem_handler
+}
+
+#[test]
+fn test_position_and_immediate() {
+    let mem_handler = run_program(vec![1002, 4, 3, 4, 33], &[]);
+    assert!(mem_handler.memory() == [1002, 4, 3, 4, 99]);
+
+    let mem_handler = run_program(vec![1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50], &[]);
+    assert!(mem_handler.memory()[0] == 3500);
+}
+
+#[test]
+fn test_input_queue() {
+    // Outputs the sum of its two inputs
+    let program = vec![3, 11, 3, 12, 1, 11, 12, 13, 4, 13, 99, 0, 0, 0];
+    let mem_handler = run_program(program, &[7, 35]);
+    assert!(mem_handler.get_result() == Some(vec![42]));
+}
+
+#[test]
+fn test_jumps() {
+    // Outputs 999 if the input is below 8, 1000 if it is 8 and 1001 above 8
+    let program = vec![
+        3, 21, 1008, 21, 8, 20, 1005, 20, 22, 107, 8, 21, 20, 1006, 20, 31, 1106, 0, 36, 98, 0, 0,
+        1002, 21, 125, 20, 4, 20, 1105, 1, 46, 104, 999, 1105, 1, 46, 1101, 1000, 1, 20, 4, 20,
+        1105, 1, 46, 98, 99,
+    ];
+    assert!(run_program(program.clone(), &[7]).last_result() == Some(999));
+    assert!(run_program(program.clone(), &[8]).last_result() == Some(1000));
+    assert!(run_program(program, &[9]).last_result() == Some(1001));
+}
+
+#[test]
+fn test_relative_base() {
+    // Quine, outputs a copy of itself
+    let program = vec![
+        109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+    ];
+    let mem_handler = run_program(program.clone(), &[]);
+    assert!(mem_handler.get_result() == Some(program));
+
+    let mem_handler = run_program(vec![104, 1125899906842624, 99], &[]);
+    assert!(mem_handler.last_result() == Some(1125899906842624));
+}
+
+#[test]
+fn test_run_status() {
+    // Echoes its input twice
+    let mut mem_handler = MemHandler::new(vec![3, 9, 4, 9, 4, 9, 99, 0, 0, 0]);
+    assert!(mem_handler.run() == Ok(RunStatus::NeedsInput));
+    assert!(mem_handler.run_until_event() == Ok(RunStatus::NeedsInput));
+
+    mem_handler.add_input(5);
+    assert!(mem_handler.run() == Ok(RunStatus::Stepped));
+    assert!(mem_handler.run_until_event() == Ok(RunStatus::Output(5)));
+    assert!(mem_handler.run_until_event() == Ok(RunStatus::Output(5)));
+    assert!(mem_handler.run_until_event() == Ok(RunStatus::Halted));
+    assert!(mem_handler.finished());
+    assert!(mem_handler.run() == Ok(RunStatus::Halted));
+}
+
+#[test]
+fn test_faults() {
+    let mut mem_handler = MemHandler::new(vec![1, 0, 0, 0, 42]);
+    assert!(
+        mem_handler.run_to_halt()
+            == Err(VmError::UnknownOpcode {
+                address: 4,
+                op_code: 42
+            })
+    );
+    // The faulting instruction is retried, and fails again
+    assert!(
+        mem_handler.run()
+            == Err(VmError::UnknownOpcode {
+                address: 4,
+                op_code: 42
+            })
+    );
+
+    let mut mem_handler = MemHandler::new(vec![1101, 1, 1, 5, 99]);
+    assert!(mem_handler.run() == Ok(RunStatus::Stepped));
+    assert!(mem_handler.run() == Ok(RunStatus::Halted));
+
+    let mut mem_handler = MemHandler::new(vec![11101, 1, 1, 5, 99]);
+    assert!(mem_handler.run() == Err(VmError::WriteToImmediate { address: 0 }));
+
+    let mut mem_handler = MemHandler::new(vec![109, -10, 204, 0, 99]);
+    assert!(
+        mem_handler.run_to_halt()
+            == Err(VmError::NegativeAddress {
+                address: 2,
+                position: -10
+            })
+    );
+
+    let mut mem_handler = MemHandler::new(vec![1101, 1, 1, 1000, 99]);
+    mem_handler.set_memory_limit(100);
+    assert!(
+        mem_handler.run()
+            == Err(VmError::OutOfMemory {
+                address: 0,
+                position: 1000
+            })
+    );
+    assert!(
+        mem_handler.poke(usize::MAX, 1)
+            == Err(VmError::OutOfMemory {
+                address: 0,
+                position: usize::MAX
+            })
+    );
+}
+
+#[test]
+fn test_watchpoints() {
+    use std::sync::{Arc, Mutex};
+
+    // Adds 1 to address 13, outputs it, then patches its own exit
+    let program = vec![1001, 13, 1, 13, 4, 13, 1101, 0, 99, 10, 0, 0, 0, 5];
+    let mut mem_handler = MemHandler::new(program);
+    let reads = Arc::new(Mutex::new(vec![]));
+    let seen = reads.clone();
+    mem_handler.watch(
+        13..=13,
+        Access::Read,
+        WatchAction::Callback(Box::new(move |hit| seen.lock().unwrap().push(hit.pc))),
+    );
+    mem_handler.watch(0..=12, Access::Write, WatchAction::Pause);
+
+    assert!(mem_handler.run_until_event() == Ok(RunStatus::Output(6)));
+    let hit = WatchHit {
+        pc: 6,
+        address: 10,
+        access: Access::Write,
+        value: 99,
+    };
+    assert!(mem_handler.run_until_event() == Ok(RunStatus::Watch(hit)));
+    assert!(mem_handler.pc() == 10);
+    assert!(mem_handler.run_until_event() == Ok(RunStatus::Halted));
+    assert!(*reads.lock().unwrap() == vec![0, 4]);
+}
+
+#[test]
+fn test_instruction_budget() {
+    // Counts address 5 up forever
+    let mut mem_handler = MemHandler::new(vec![101, 1, 5, 5, 1105, 1, 0]);
+    mem_handler.set_instruction_budget(10);
+    assert!(mem_handler.run_until_event() == Ok(RunStatus::OutOfBudget));
+    assert!(mem_handler.executed() == 10 && mem_handler.peek(5) == 6);
+    assert!(mem_handler.run() == Ok(RunStatus::OutOfBudget));
+
+    mem_handler.set_instruction_budget(1);
+    assert!(mem_handler.run() == Ok(RunStatus::Stepped));
+    assert!(mem_handler.executed() == 11);
+
+    // Memory changes every iteration, so this is progress
+    mem_handler.detect_no_progress();
+    mem_handler.set_instruction_budget(1000);
+    assert!(mem_handler.run_until_event() == Ok(RunStatus::OutOfBudget));
+}
+
+#[test]
+fn test_no_progress() {
+    // Keeps writing the same value, then jumps back
+    let program = vec![1101, 1, 2, 9, 109, 0, 1105, 1, 0, 0];
+    let mut mem_handler = MemHandler::new(program);
+    mem_handler.detect_no_progress();
+    assert!(mem_handler.run_until_event() == Ok(RunStatus::NoProgress));
+    assert!(mem_handler.pc() == 4 && mem_handler.executed() == 4);
+
+    // Waiting for input is not a lack of progress
+    let mut mem_handler = MemHandler::new(vec![3, 5, 1105, 1, 0, 0]);
+    mem_handler.detect_no_progress();
+    assert!(mem_handler.run_until_event() == Ok(RunStatus::NeedsInput));
+    assert!(mem_handler.run_until_event() == Ok(RunStatus::NeedsInput));
+    mem_handler.add_input(3);
+    mem_handler.add_input(3);
+    assert!(mem_handler.run_until_event() == Ok(RunStatus::NeedsInput));
+
+    // Only moves the relative base, which runs into the budget without
+    // remembering more than the loop itself
+    let mut mem_handler = MemHandler::new(vec![109, 1, 1105, 1, 0]);
+    mem_handler.detect_no_progress();
+    mem_handler.set_instruction_budget(1_000_000);
+    assert!(mem_handler.run_until_event() == Ok(RunStatus::OutOfBudget));
+    assert!(mem_handler.relative_base() == 500_000);
+    assert!(mem_handler.seen.as_ref().unwrap().len() <= 1);
+
+    // Waits for address 7 to be set, which a poke does
+    let mut mem_handler = MemHandler::new(vec![1006, 7, 0, 104, 5, 99, 0, 0]);
+    mem_handler.detect_no_progress();
+    assert!(mem_handler.run_until_event() == Ok(RunStatus::NoProgress));
+    mem_handler.add_input(1);
+    assert!(mem_handler.seen.as_ref().unwrap().is_empty());
+    assert!(mem_handler.run_until_event() == Ok(RunStatus::NoProgress));
+    mem_handler.poke(7, 1).unwrap();
+    assert!(mem_handler.run_until_event() == Ok(RunStatus::Output(5)));
+}
+
+#[test]
+fn test_decode_cache() {
+    // Adds two numbers, then patches the add into a multiply and runs it again
+    let program = vec![
+        1, 21, 22, 21, 4, 21, 1006, 23, 20, 1101, 0, 0, 23, 1101, 2, 0, 0, 1105, 1, 0, 99, 3, 4, 1,
+    ];
+    let mut mem_handler = MemHandler::new(program.clone());
+    mem_handler.enable_decode_cache();
+    assert!(mem_handler.run_to_halt() == Ok(RunStatus::Halted));
+    assert!(mem_handler.get_result() == Some(vec![7, 28]));
+    assert!(mem_handler.memory() == run_program(program, &[]).memory());
+
+    // Code far out in paged memory is cached without the addresses before it
+    use crate::memory::PagedMemory;
+    let far: i64 = 1 << 40;
+    let mut mem_handler = MemHandler::with_memory(PagedMemory::from(vec![1105, 1, far]));
+    for (offset, val) in [104, 5, 99].iter().enumerate() {
+        mem_handler.poke(far as usize + offset, *val).unwrap();
+    }
+    mem_handler.enable_decode_cache();
+    assert!(mem_handler.run_to_halt() == Ok(RunStatus::Halted));
+    assert!(mem_handler.get_result() == Some(vec![5]));
+    let decoded = mem_handler.decoded.as_ref().unwrap();
+    assert!(decoded.near.len() == 1 && decoded.far.len() == 2);
+}
+
+#[test]
+fn test_arithmetic() {
+    // Doubles address 9 until it is 0
+    let program = vec![1002, 9, 2, 9, 1005, 9, 0, 99, 0, 1];
+    let mut mem_handler = MemHandler::new(program.clone());
+    assert!(mem_handler.run_to_halt() == Ok(RunStatus::Halted));
+    assert!(mem_handler.peek(9) == 0);
+
+    let mut mem_handler = MemHandler::new(program.clone());
+    mem_handler.set_arithmetic(Arithmetic::Checked);
+    assert!(mem_handler.run_to_halt() == Err(VmError::Overflow { address: 0 }));
+    assert!(mem_handler.peek(9) == 1 << 62);
+
+    let mut mem_handler = MemHandler::new(program.clone());
+    mem_handler.set_arithmetic(Arithmetic::Saturating);
+    mem_handler.set_instruction_budget(1000);
+    assert!(mem_handler.run_to_halt() == Ok(RunStatus::OutOfBudget));
+    assert!(mem_handler.peek(9) == i64::MAX);
+
+    // Continues with i128 words, which then overflow as checked
+    let mut mem_handler = MemHandler::new(program);
+    mem_handler.set_arithmetic(Arithmetic::Widening);
+    mem_handler.set_instruction_budget(1000);
+    assert!(mem_handler.run_to_halt() == Ok(RunStatus::NeedsWiderWords));
+    assert!(mem_handler.pc() == 0 && mem_handler.executed() == 124);
+    let mut mem_handler = mem_handler.widen();
+    assert!(mem_handler.peek(9) == 1 << 62);
+    assert!(mem_handler.run_to_halt() == Err(VmError::Overflow { address: 0 }));
+    assert!(mem_handler.peek(9) == 1 << 126 && mem_handler.executed() == 124 + 128);
+
+    // Set directly on i128 words it can not widen any further
+    let mut mem_handler = MemHandler::with_words(vec![1002_i128, 9, 2, 9, 1105, 1, 0, 0, 0, 1]);
+    mem_handler.set_arithmetic(Arithmetic::Widening);
+    assert!(mem_handler.run_to_halt() == Err(VmError::Overflow { address: 0 }));
+    assert!(mem_handler.peek(9) == 1 << 126);
+}
+
+#[test]
+fn test_word_types() {
+    use crate::parser;
+    use num_bigint::BigInt;
+
+    // Squares its input twice and outputs it
+    let text = "3,13,2,13,13,13,2,13,13,13,4,13,99,0";
+    let input = 1 << 40;
+
+    let mut mem_handler = MemHandler::new(parser::parse_str(text));
+    mem_handler.set_arithmetic(Arithmetic::Checked);
+    mem_handler.add_input(input);
+    assert!(mem_handler.run_to_halt() == Err(VmError::Overflow { address: 2 }));
+
+    let mut mem_handler = MemHandler::with_words(parser::parse_str_as::<i128>(text));
+    mem_handler.set_arithmetic(Arithmetic::Checked);
+    mem_handler.add_input(input as i128);
+    assert!(mem_handler.run_to_halt() == Err(VmError::Overflow { address: 6 }));
+    assert!(mem_handler.peek(13) == 1 << 80);
+
+    let mut mem_handler = MemHandler::with_words(parser::parse_str_as::<BigInt>(text));
+    mem_handler.add_input(BigInt::from(input));
+    assert!(mem_handler.run_to_halt() == Ok(RunStatus::Halted));
+    let expected = "1461501637330902918203684832716283019655932542976";
+    assert!(mem_handler.last_result().unwrap().to_string() == expected);
+
+    // Words used as addresses still have to fit
+    let far = BigInt::from(i64::MAX) * 2;
+    let mut mem_handler = MemHandler::with_words(vec![BigInt::from(4), far, BigInt::from(99)]);
+    assert!(
+        mem_handler.run()
+            == Err(VmError::OutOfMemory {
+                address: 0,
+                position: usize::MAX
+            })
+    );
+}
+
+#[test]
+fn test_source_and_sink() {
+    use crate::io;
+    use std::sync::mpsc;
+
+    // Outputs input + 1 for every input
+    let program = vec![3, 11, 101, 1, 11, 11, 4, 11, 1105, 1, 0, 0];
+    let mut mem_handler = MemHandler::new(program);
+    mem_handler.add_input(10);
+    mem_handler.set_input_source(io::iter_source(1..=3));
+    let (sender, outputs) = mpsc::channel();
+    mem_handler.set_output_sink(move |val| sender.send(val).unwrap());
+
+    // The queue comes first, the source once it is empty
+    assert!(mem_handler.run_to_halt() == Ok(RunStatus::NeedsInput));
+    assert!(outputs.try_iter().collect::<Vec<i64>>() == vec![11, 2, 3, 4]);
+    assert!(mem_handler.get_result().is_none());
+
+    mem_handler.add_input(5);
+    assert!(mem_handler.run_until_event() == Ok(RunStatus::Output(6)));
+    assert!(outputs.try_recv() == Ok(6));
+}
@@ -0,0 +1,182 @@
+// Memory backends for MemHandler. Memory that was never written reads as 0.
+//
+// DenseMemory keeps everything in one vector that grows up to the highest
+// address written, which is the fastest for normal programs. PagedMemory
+// only allocates fixed size pages that are actually written, so a single
+// write to a far away address stays cheap. Both hold i64 words unless told
+// otherwise, e.g. DenseMemory<BigInt>.
+use crate::word::Word;
+use std::collections::HashMap;
+
+pub trait Memory: Clone + From<Vec<<Self as Memory>::Word>> {
+    type Word: Word;
+
+    fn get(&self, address: usize) -> Self::Word;
+
+    fn set(&mut self, address: usize, val: Self::Word);
+
+    // Number of words allocated once address has been written, used to
+    // enforce the memory limit before writing. None if that many words do
+    // not even fit in a usize.
+    fn allocated_with(&self, address: usize) -> Option<usize>;
+
+    // Allocated memory as (start address, words) blocks in address order
+    fn blocks(&self) -> Vec<(usize, &[Self::Word])>;
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DenseMemory<W = i64> {
+    raw: Vec<W>,
+}
+
+impl<W> DenseMemory<W> {
+    pub fn as_slice(&self) -> &[W] {
+        &self.raw
+    }
+}
+
+impl<W> From<Vec<W>> for DenseMemory<W> {
+    fn from(raw: Vec<W>) -> Self {
+        DenseMemory { raw }
+    }
+}
+
+impl<W: Word> Memory for DenseMemory<W> {
+    type Word = W;
+
+    #[inline]
+    fn get(&self, address: usize) -> W {
+        match self.raw.get(address) {
+            Some(val) => val.clone(),
+            None => W::from(0),
+        }
+    }
+
+    #[inline]
+    fn set(&mut self, address: usize, val: W) {
+        // Dynamic allocate
+        if address >= self.raw.len() {
+            self.raw.resize(address + 1, W::from(0));
+        }
+
+        self.raw[address] = val;
+    }
+
+    fn allocated_with(&self, address: usize) -> Option<usize> {
+        Some(self.raw.len().max(address.checked_add(1)?))
+    }
+
+    fn blocks(&self) -> Vec<(usize, &[W])> {
+        vec![(0, &self.raw)]
+    }
+}
+
+pub const PAGE_SIZE: usize = 1024;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PagedMemory<W = i64> {
+    pages: HashMap<usize, Box<[W]>>,
+}
+
+impl<W: Word> PagedMemory<W> {
+    pub fn new() -> Self {
+        PagedMemory {
+            pages: HashMap::new(),
+        }
+    }
+
+    // Number of pages currently allocated
+    pub fn nr_of_pages(&self) -> usize {
+        self.pages.len()
+    }
+}
+
+impl<W: Word> From<Vec<W>> for PagedMemory<W> {
+    fn from(raw: Vec<W>) -> Self {
+        let mut memory = PagedMemory::new();
+        for (page, words) in raw.chunks(PAGE_SIZE).enumerate() {
+            let mut data = vec![W::from(0); PAGE_SIZE];
+            data[..words.len()].clone_from_slice(words);
+            memory.pages.insert(page, data.into_boxed_slice());
+        }
+        memory
+    }
+}
+
+impl<W: Word> Memory for PagedMemory<W> {
+    type Word = W;
+
+    fn get(&self, address: usize) -> W {
+        match self.pages.get(&(address / PAGE_SIZE)) {
+            Some(page) => page[address % PAGE_SIZE].clone(),
+            None => W::from(0),
+        }
+    }
+
+    fn set(&mut self, address: usize, val: W) {
+        let page = self
+            .pages
+            .entry(address / PAGE_SIZE)
+            .or_insert_with(|| vec![W::from(0); PAGE_SIZE].into_boxed_slice());
+        page[address % PAGE_SIZE] = val;
+    }
+
+    fn allocated_with(&self, address: usize) -> Option<usize> {
+        let allocated = self.pages.len() * PAGE_SIZE;
+        if self.pages.contains_key(&(address / PAGE_SIZE)) {
+            Some(allocated)
+        } else {
+            allocated.checked_add(PAGE_SIZE)
+        }
+    }
+
+    fn blocks(&self) -> Vec<(usize, &[W])> {
+        let mut blocks: Vec<(usize, &[W])> = self
+            .pages
+            .iter()
+            .map(|(page, data)| (page * PAGE_SIZE, &data[..]))
+            .collect();
+        blocks.sort_by_key(|(start, _)| *start);
+        blocks
+    }
+}
+
+#[test]
+fn test_dense_memory() {
+    let mut memory: DenseMemory = DenseMemory::from(vec![1, 2, 3]);
+    assert!(memory.get(1) == 2 && memory.get(100) == 0);
+    assert!(memory.allocated_with(1) == Some(3) && memory.allocated_with(9) == Some(10));
+    assert!(memory.allocated_with(usize::MAX).is_none());
+
+    memory.set(5, 7);
+    assert!(memory.as_slice() == [1, 2, 3, 0, 0, 7]);
+}
+
+#[test]
+fn test_paged_memory() {
+    let mut memory: PagedMemory = PagedMemory::from(vec![1, 2, 3]);
+    assert!(memory.get(2) == 3 && memory.get(3) == 0);
+    assert!(memory.nr_of_pages() == 1);
+
+    let far = 1_000_000_000_000;
+    assert!(memory.allocated_with(far) == Some(2 * PAGE_SIZE));
+    memory.set(far, -4);
+    assert!(memory.get(far) == -4 && memory.get(far + 1) == 0);
+    assert!(memory.nr_of_pages() == 2);
+
+    let blocks = memory.blocks();
+    assert!(blocks.len() == 2 && blocks[0].0 == 0);
+    assert!(blocks[1].0 == far - far % PAGE_SIZE);
+}
+
+#[test]
+fn test_far_writes() {
+    use crate::mem_handler::{MemHandler, RunStatus};
+
+    // Writes to and reads back from address 10^9 through the relative base
+    let program: Vec<i64> = vec![109, 1_000_000_000, 21101, 6, 7, 0, 204, 0, 99];
+    let mut mem_handler = MemHandler::with_memory(PagedMemory::from(program));
+    assert!(mem_handler.run_to_halt() == Ok(RunStatus::Halted));
+    assert!(mem_handler.last_result() == Some(13));
+    assert!(mem_handler.snapshot().memory.nr_of_pages() == 2);
+}
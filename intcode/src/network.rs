@@ -0,0 +1,268 @@
+// Network of intcode machines where the output of a machine is sent as input
+// to the machines it is connected to. A machine connected to several others
+// sends every output to all of them (fan-out), a machine with several
+// machines connected to it reads their outputs in the order they were
+// produced (fan-in). E.g. the day 7 amplifiers in a feedback loop:
+//
+//     let mut network = Network::ring(amplifiers);
+//     network.add_input(0, 0);
+//     let signal = network.run()?;
+//
+// Machines are run one at a time on the calling thread, each until it waits
+// for input or halts, until none of them can continue. The signal is the
+// last value produced by the output node, the last machine unless changed
+// with set_output. If the output node is still waiting for input at that
+// point the network is deadlocked, every machine left is waiting for input
+// and there are no values on their way to any of them.
+use crate::error::VmError;
+use crate::mem_handler::{MemHandler, RunStatus};
+use crate::memory::{DenseMemory, Memory};
+use std::error::Error;
+use std::fmt;
+
+// W is the word type of the machines
+#[derive(Clone, Debug, PartialEq)]
+pub enum NetworkError<W = i64> {
+    Fault { node: usize, error: VmError },
+    // A machine stopped with a status the network can not handle, e.g. when
+    // its instruction budget ran out
+    Stopped { node: usize, status: RunStatus<W> },
+    // The output node never produced a value
+    NoSignal,
+    // Machines waiting for input that will never come, as (node, pc)
+    Deadlock { blocked: Vec<(usize, usize)> },
+}
+
+impl<W: fmt::Debug> fmt::Display for NetworkError<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NetworkError::Fault { node, error } => write!(f, "machine {} failed: {}", node, error),
+            NetworkError::Stopped { node, status } => {
+                write!(f, "machine {} stopped: {:?}", node, status)
+            }
+            NetworkError::NoSignal => write!(f, "the output machine produced no signal"),
+            NetworkError::Deadlock { blocked } => {
+                let blocked: Vec<String> = blocked
+                    .iter()
+                    .map(|(node, pc)| format!("machine {} at {}", node, pc))
+                    .collect();
+                write!(f, "deadlock, waiting for input: {}", blocked.join(", "))
+            }
+        }
+    }
+}
+
+impl<W: fmt::Debug> Error for NetworkError<W> {}
+
+pub struct Network<M: Memory = DenseMemory> {
+    nodes: Vec<MemHandler<M>>,
+    // Machines receiving the output of each machine
+    connections: Vec<Vec<usize>>,
+    output: usize,
+    signal: Option<M::Word>,
+}
+
+impl<M: Memory> Network<M> {
+    // Machines without any connections
+    pub fn new(nodes: Vec<MemHandler<M>>) -> Self {
+        let connections = vec![vec![]; nodes.len()];
+        let output = nodes.len().saturating_sub(1);
+        Network {
+            nodes,
+            connections,
+            output,
+            signal: None,
+        }
+    }
+
+    // Every machine sends its output to the next one
+    pub fn chain(nodes: Vec<MemHandler<M>>) -> Self {
+        let mut network = Network::new(nodes);
+        for node in 1..network.nodes.len() {
+            network.connect(node - 1, node);
+        }
+        network
+    }
+
+    // A chain where the last machine also sends its output to the first one
+    pub fn ring(nodes: Vec<MemHandler<M>>) -> Self {
+        let mut network = Network::chain(nodes);
+        if !network.nodes.is_empty() {
+            network.connect(network.nodes.len() - 1, 0);
+        }
+        network
+    }
+
+    pub fn connect(&mut self, from: usize, to: usize) {
+        self.connections[from].push(to);
+    }
+
+    pub fn add_input(&mut self, node: usize, val: M::Word) {
+        self.nodes[node].add_input(val);
+    }
+
+    // The machine whose last output is the signal returned by run
+    pub fn set_output(&mut self, node: usize) {
+        self.output = node;
+    }
+
+    pub fn node(&self, node: usize) -> &MemHandler<M> {
+        &self.nodes[node]
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    // Runs until none of the machines can continue and returns the signal
+    pub fn run(&mut self) -> Result<M::Word, NetworkError<M::Word>> {
+        loop {
+            let mut progress = false;
+            for node in 0..self.nodes.len() {
+                let executed = self.nodes[node].executed();
+                self.run_node(node)?;
+                progress |= self.nodes[node].executed() != executed;
+            }
+
+            if !progress {
+                break;
+            }
+        }
+
+        // No output node, e.g. in an empty network
+        let output = self.nodes.get(self.output).ok_or(NetworkError::NoSignal)?;
+        if !output.finished() {
+            let blocked = (0..self.nodes.len())
+                .filter(|node| !self.nodes[*node].finished())
+                .map(|node| (node, self.nodes[node].pc()))
+                .collect();
+            return Err(NetworkError::Deadlock { blocked });
+        }
+        self.signal.clone().ok_or(NetworkError::NoSignal)
+    }
+
+    // Runs a single machine until it waits for input or halts, delivering
+    // its outputs on the way
+    fn run_node(&mut self, node: usize) -> Result<(), NetworkError<M::Word>> {
+        loop {
+            match self.nodes[node].run_until_event() {
+                Ok(RunStatus::Output(val)) => {
+                    for index in 0..self.connections[node].len() {
+                        let to = self.connections[node][index];
+                        self.nodes[to].add_input(val.clone());
+                    }
+                    if node == self.output {
+                        self.signal = Some(val);
+                    }
+                }
+                Ok(RunStatus::NeedsInput) | Ok(RunStatus::Halted) => return Ok(()),
+                Ok(status) => return Err(NetworkError::Stopped { node, status }),
+                Err(error) => return Err(NetworkError::Fault { node, error }),
+            }
+        }
+    }
+}
+
+#[test]
+fn test_chain_and_ring() {
+    use crate::parser;
+
+    // Day 7 examples, every amplifier reads a phase and a signal
+    let program = parser::parse_str("3,15,3,16,1002,16,10,16,1,16,15,15,4,15,99,0,0");
+    let amplifiers = (0..5).map(|_| MemHandler::new(program.clone())).collect();
+    let mut network = Network::chain(amplifiers);
+    for (node, phase) in [4, 3, 2, 1, 0].iter().enumerate() {
+        network.add_input(node, *phase);
+    }
+    network.add_input(0, 0);
+    assert!(network.run() == Ok(43210));
+    assert!((0..5).all(|node| network.node(node).finished()));
+
+    let program = parser::parse_str(
+        "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5",
+    );
+    let amplifiers = (0..5).map(|_| MemHandler::new(program.clone())).collect();
+    let mut network = Network::ring(amplifiers);
+    for (node, phase) in [9, 8, 7, 6, 5].iter().enumerate() {
+        network.add_input(node, *phase);
+    }
+    network.add_input(0, 0);
+    assert!(network.run() == Ok(139629729));
+}
+
+#[test]
+fn test_fan_out_and_in() {
+    // Outputs its input, twice
+    let double = vec![3, 9, 4, 9, 4, 9, 1105, 1, 0, 0];
+    // Outputs the sum of every two inputs
+    let add = vec![3, 11, 3, 12, 1, 11, 12, 11, 4, 11, 99, 0, 0];
+
+    let nodes = vec![
+        MemHandler::new(double.clone()),
+        MemHandler::new(double),
+        MemHandler::new(add.clone()),
+        MemHandler::new(add),
+    ];
+    let mut network = Network::new(nodes);
+    network.connect(0, 1);
+    network.connect(0, 2);
+    network.connect(1, 3);
+    network.connect(2, 3);
+    network.set_output(3);
+    network.add_input(0, 5);
+
+    assert!(network.run() == Ok(10));
+    assert!(network.node(2).last_result() == Some(10));
+    assert!(network.node(1).get_result() == Some(vec![5, 5, 5, 5]));
+}
+
+#[test]
+fn test_errors() {
+    let nodes = (0..2).map(|_| MemHandler::new(vec![3, 0, 99])).collect();
+    let mut network = Network::chain(nodes);
+    network.add_input(0, 1);
+    network.add_input(1, 1);
+    assert!(network.run() == Err(NetworkError::NoSignal));
+
+    let mut network: Network = Network::ring(vec![]);
+    assert!(network.is_empty() && network.run() == Err(NetworkError::NoSignal));
+
+    let nodes = (0..2).map(|_| MemHandler::new(vec![104, 1, 42])).collect();
+    let mut network = Network::chain(nodes);
+    let error = network.run().unwrap_err();
+    assert!(error.to_string() == "machine 0 failed: unknown op code 42 at address 2");
+}
+
+#[test]
+fn test_deadlock() {
+    // Both machines wait for the other to send something first
+    let nodes = (0..2)
+        .map(|_| MemHandler::new(vec![104, 1, 3, 9, 3, 9, 4, 9, 99, 0]))
+        .collect();
+    let mut network = Network::ring(nodes);
+    let error = network.run().unwrap_err();
+    assert!(
+        error
+            == NetworkError::Deadlock {
+                blocked: vec![(0, 4), (1, 4)]
+            }
+    );
+    assert!(error.to_string() == "deadlock, waiting for input: machine 0 at 4, machine 1 at 4");
+
+    // The first machine halted, leaving the second without input
+    let nodes = vec![
+        MemHandler::new(vec![104, 1, 99]),
+        MemHandler::new(vec![3, 9, 3, 9, 4, 9, 99]),
+    ];
+    let mut network = Network::chain(nodes);
+    assert!(
+        network.run()
+            == Err(NetworkError::Deadlock {
+                blocked: vec![(1, 2)]
+            })
+    );
+}
@@ -0,0 +1,196 @@
+use crate::error::VmError;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ParamMode {
+    Position,
+    Immediate,
+    Relative,
+}
+
+impl ParamMode {
+    pub fn from_num(mode: i64) -> Option<Self> {
+        match mode {
+            0 => Some(ParamMode::Position),
+            1 => Some(ParamMode::Immediate),
+            2 => Some(ParamMode::Relative),
+            _ => None,
+        }
+    }
+
+    pub fn to_num(self) -> i64 {
+        match self {
+            ParamMode::Position => 0,
+            ParamMode::Immediate => 1,
+            ParamMode::Relative => 2,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum OpCode {
+    Add,
+    Multiply,
+    Input,
+    Output,
+    JumpIfTrue,
+    JumpIfFalse,
+    LessThan,
+    Equal,
+    ChangeRelative,
+    Exit,
+}
+
+impl OpCode {
+    pub fn from_num(op_code: i64) -> Option<Self> {
+        match op_code {
+            1 => Some(OpCode::Add),
+            2 => Some(OpCode::Multiply),
+            3 => Some(OpCode::Input),
+            4 => Some(OpCode::Output),
+            5 => Some(OpCode::JumpIfTrue),
+            6 => Some(OpCode::JumpIfFalse),
+            7 => Some(OpCode::LessThan),
+            8 => Some(OpCode::Equal),
+            9 => Some(OpCode::ChangeRelative),
+            99 => Some(OpCode::Exit),
+            _ => None,
+        }
+    }
+
+    // Inverse of mnemonic, ignoring case
+    pub fn from_mnemonic(mnemonic: &str) -> Option<Self> {
+        match mnemonic.to_uppercase().as_str() {
+            "ADD" => Some(OpCode::Add),
+            "MUL" => Some(OpCode::Multiply),
+            "IN" => Some(OpCode::Input),
+            "OUT" => Some(OpCode::Output),
+            "JT" => Some(OpCode::JumpIfTrue),
+            "JF" => Some(OpCode::JumpIfFalse),
+            "LT" => Some(OpCode::LessThan),
+            "EQ" => Some(OpCode::Equal),
+            "ARB" => Some(OpCode::ChangeRelative),
+            "HLT" => Some(OpCode::Exit),
+            _ => None,
+        }
+    }
+
+    // The number used for the op code in an instruction
+    pub fn to_num(self) -> i64 {
+        match self {
+            OpCode::Add => 1,
+            OpCode::Multiply => 2,
+            OpCode::Input => 3,
+            OpCode::Output => 4,
+            OpCode::JumpIfTrue => 5,
+            OpCode::JumpIfFalse => 6,
+            OpCode::LessThan => 7,
+            OpCode::Equal => 8,
+            OpCode::ChangeRelative => 9,
+            OpCode::Exit => 99,
+        }
+    }
+
+    // Number of parameters following the instruction
+    pub fn nr_of_params(self) -> usize {
+        match self {
+            OpCode::Add | OpCode::Multiply | OpCode::LessThan | OpCode::Equal => 3,
+            OpCode::JumpIfTrue | OpCode::JumpIfFalse => 2,
+            OpCode::Input | OpCode::Output | OpCode::ChangeRelative => 1,
+            OpCode::Exit => 0,
+        }
+    }
+
+    // Whether the last parameter is the address the result is written to
+    pub fn writes(self) -> bool {
+        matches!(
+            self,
+            OpCode::Add | OpCode::Multiply | OpCode::Input | OpCode::LessThan | OpCode::Equal
+        )
+    }
+
+    // Short name used in listings
+    pub fn mnemonic(self) -> &'static str {
+        match self {
+            OpCode::Add => "ADD",
+            OpCode::Multiply => "MUL",
+            OpCode::Input => "IN",
+            OpCode::Output => "OUT",
+            OpCode::JumpIfTrue => "JT",
+            OpCode::JumpIfFalse => "JF",
+            OpCode::LessThan => "LT",
+            OpCode::Equal => "EQ",
+            OpCode::ChangeRelative => "ARB",
+            OpCode::Exit => "HLT",
+        }
+    }
+}
+
+// Splits the instruction word found at address into its op code and the
+// modes of its three (possible) parameters. Modes of parameters the op code
+// does not take are left as position mode.
+pub fn decode(address: usize, instruction: i64) -> Result<(OpCode, [ParamMode; 3]), VmError> {
+    let op_code = OpCode::from_num(instruction % 100).ok_or(VmError::UnknownOpcode {
+        address,
+        op_code: instruction,
+    })?;
+
+    let mut next_modes = instruction / 100;
+    let mut modes = [ParamMode::Position; 3];
+
+    for mode in modes.iter_mut().take(op_code.nr_of_params()) {
+        *mode = ParamMode::from_num(next_modes % 10).ok_or(VmError::BadParamMode {
+            address,
+            mode: next_modes % 10,
+        })?;
+        next_modes /= 10;
+    }
+
+    Ok((op_code, modes))
+}
+
+#[test]
+fn test_decode() {
+    let (op_code, modes) = decode(0, 1002).unwrap();
+    assert!(op_code == OpCode::Multiply);
+    assert!(
+        modes
+            == [
+                ParamMode::Position,
+                ParamMode::Immediate,
+                ParamMode::Position
+            ]
+    );
+
+    let (op_code, modes) = decode(0, 21107).unwrap();
+    assert!(op_code == OpCode::LessThan);
+    assert!(
+        modes
+            == [
+                ParamMode::Immediate,
+                ParamMode::Immediate,
+                ParamMode::Relative
+            ]
+    );
+
+    assert!(
+        decode(4, 42)
+            == Err(VmError::UnknownOpcode {
+                address: 4,
+                op_code: 42
+            })
+    );
+    assert!(
+        decode(4, -1)
+            == Err(VmError::UnknownOpcode {
+                address: 4,
+                op_code: -1
+            })
+    );
+    assert!(
+        decode(7, 304)
+            == Err(VmError::BadParamMode {
+                address: 7,
+                mode: 3
+            })
+    );
+}
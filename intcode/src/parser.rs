@@ -0,0 +1,72 @@
+use crate::word::Word;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+pub fn parse_file(filename: String) -> Vec<i64> {
+    parse_file_as(filename)
+}
+
+pub fn parse_str(input: &str) -> Vec<i64> {
+    parse_str_as(input)
+}
+
+// Parses a program into words of any type, e.g. BigInt for programs with
+// numbers that do not fit in an i64
+pub fn parse_file_as<W: Word>(filename: String) -> Vec<W> {
+    let file = File::open(filename).expect("Can not open file...");
+
+    let mut int_codes: Vec<W> = Vec::new();
+    let buffered = BufReader::new(file);
+
+    for line in buffered.lines() {
+        match line {
+            Ok(l) => int_codes.extend(parse_str_as(&l)),
+            Err(_) => eprintln!("Failed to read line"),
+        }
+    }
+
+    int_codes
+}
+
+pub fn parse_str_as<W: Word>(input: &str) -> Vec<W> {
+    input
+        .split(',')
+        .map(|n| n.trim())
+        .filter(|n| !n.is_empty())
+        .map(|n| match n.parse::<W>() {
+            Ok(word) => word,
+            Err(_) => panic!("Can not parse '{}'...", n),
+        })
+        .collect()
+}
+
+// Writes a program in the same comma separated format that is parsed
+pub fn to_str<W: fmt::Display>(program: &[W]) -> String {
+    let words: Vec<String> = program.iter().map(|w| w.to_string()).collect();
+    words.join(",")
+}
+
+#[test]
+fn test_parse_str() {
+    assert!(parse_str("1002,4,3,4,33") == vec![1002, 4, 3, 4, 33]);
+    assert!(parse_str("104,-1,99\n") == vec![104, -1, 99]);
+}
+
+#[test]
+fn test_to_str() {
+    let program = vec![1002, 4, 3, 4, -33];
+    assert!(to_str(&program) == "1002,4,3,4,-33");
+    assert!(parse_str(&to_str(&program)) == program);
+}
+
+#[test]
+fn test_parse_str_as() {
+    use num_bigint::BigInt;
+
+    let text = "104,170141183460469231731687303715884105727,99";
+    assert!(parse_str_as::<i128>(text)[1] == i128::MAX);
+    let program: Vec<BigInt> = parse_str_as(text);
+    assert!(program[1] == BigInt::from(i128::MAX));
+    assert!(to_str(&program) == text);
+}
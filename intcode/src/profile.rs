@@ -0,0 +1,166 @@
+// Execution profile collected by a MemHandler with profiling enabled. Counts
+// how often every op code and every address was executed, how often every
+// basic block was entered and how often backward jumps, i.e. loops, were
+// taken.
+//
+// Basic blocks are found while running: a block starts at the first
+// instruction executed and at every instruction following a jump, taken or
+// not, and is named by the address of its first instruction.
+use crate::op_code::OpCode;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Profile {
+    pub instructions: u64,
+    pub op_codes: HashMap<OpCode, u64>,
+    pub addresses: HashMap<usize, u64>,
+    pub blocks: HashMap<usize, u64>,
+    // Backward jumps taken, by (jump address, target)
+    pub loops: HashMap<(usize, usize), u64>,
+    in_block: bool,
+}
+
+// Entries sorted by count, highest first, ties by key
+fn sorted<K: Copy + Ord>(counts: &HashMap<K, u64>) -> Vec<(K, u64)> {
+    let mut entries: Vec<(K, u64)> = counts.iter().map(|(k, c)| (*k, *c)).collect();
+    entries.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    entries
+}
+
+impl Profile {
+    // Records an executed instruction, next is the address of the
+    // instruction executed after it
+    pub fn record(&mut self, pc: usize, op_code: OpCode, next: usize) {
+        self.instructions += 1;
+        *self.op_codes.entry(op_code).or_insert(0) += 1;
+        *self.addresses.entry(pc).or_insert(0) += 1;
+
+        if !self.in_block {
+            *self.blocks.entry(pc).or_insert(0) += 1;
+            self.in_block = true;
+        }
+
+        if op_code == OpCode::JumpIfTrue || op_code == OpCode::JumpIfFalse {
+            self.in_block = false;
+            if next <= pc {
+                *self.loops.entry((pc, next)).or_insert(0) += 1;
+            }
+        }
+    }
+
+    // Report with the top entries of every table
+    pub fn to_text(&self, top: usize) -> String {
+        let mut out = String::new();
+        let percent = |count: u64| 100.0 * count as f64 / self.instructions.max(1) as f64;
+
+        writeln!(out, "instructions: {}", self.instructions).unwrap();
+
+        let mut op_codes: Vec<(OpCode, u64)> =
+            self.op_codes.iter().map(|(o, c)| (*o, *c)).collect();
+        op_codes.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.to_num().cmp(&b.0.to_num())));
+        writeln!(out, "op codes:").unwrap();
+        for (op_code, count) in op_codes {
+            let mnemonic = op_code.mnemonic();
+            writeln!(
+                out,
+                "  {:<4}{:>12} {:>6.2}%",
+                mnemonic,
+                count,
+                percent(count)
+            )
+            .unwrap();
+        }
+
+        writeln!(out, "hot addresses:").unwrap();
+        for (address, count) in sorted(&self.addresses).into_iter().take(top) {
+            writeln!(
+                out,
+                "  {:04}{:>12} {:>6.2}%",
+                address,
+                count,
+                percent(count)
+            )
+            .unwrap();
+        }
+
+        writeln!(out, "hot blocks:").unwrap();
+        for (address, count) in sorted(&self.blocks).into_iter().take(top) {
+            writeln!(out, "  {:04}{:>12} entries", address, count).unwrap();
+        }
+
+        writeln!(out, "hot loops:").unwrap();
+        for ((from, to), count) in sorted(&self.loops).into_iter().take(top) {
+            writeln!(out, "  {:04}-{:04}{:>12} iterations", to, from, count).unwrap();
+        }
+
+        out
+    }
+
+    // Full report as a JSON object
+    pub fn to_json(&self) -> String {
+        let object = |entries: Vec<String>| format!("{{{}}}", entries.join(","));
+
+        let mut op_codes: Vec<(OpCode, u64)> =
+            self.op_codes.iter().map(|(o, c)| (*o, *c)).collect();
+        op_codes.sort_by_key(|(op_code, _)| op_code.to_num());
+        let op_codes = op_codes
+            .iter()
+            .map(|(op_code, count)| format!("\"{}\":{}", op_code.mnemonic(), count))
+            .collect();
+
+        let by_address = |counts: &HashMap<usize, u64>| {
+            let mut entries: Vec<(usize, u64)> = counts.iter().map(|(a, c)| (*a, *c)).collect();
+            entries.sort();
+            object(
+                entries
+                    .iter()
+                    .map(|(address, count)| format!("\"{}\":{}", address, count))
+                    .collect(),
+            )
+        };
+
+        let loops: Vec<String> = sorted(&self.loops)
+            .iter()
+            .map(|((from, to), count)| {
+                format!("{{\"from\":{},\"to\":{},\"count\":{}}}", from, to, count)
+            })
+            .collect();
+
+        format!(
+            "{{\"instructions\":{},\"op_codes\":{},\"addresses\":{},\"blocks\":{},\"loops\":[{}]}}",
+            self.instructions,
+            object(op_codes),
+            by_address(&self.addresses),
+            by_address(&self.blocks),
+            loops.join(",")
+        )
+    }
+}
+
+#[test]
+fn test_profile() {
+    use crate::mem_handler::MemHandler;
+
+    // Counts address 12 down from 3 to 0
+    let program = vec![1001, 12, -1, 12, 1005, 12, 0, 99, 0, 0, 0, 0, 3];
+    let mut mem_handler = MemHandler::new(program);
+    mem_handler.enable_profile();
+    mem_handler.run_to_halt().unwrap();
+    let profile = mem_handler.take_profile().unwrap();
+
+    assert!(profile.instructions == 7);
+    assert!(profile.op_codes[&OpCode::Add] == 3 && profile.op_codes[&OpCode::Exit] == 1);
+    assert!(profile.addresses[&0] == 3 && profile.addresses[&7] == 1);
+    assert!(profile.blocks[&0] == 3 && profile.blocks[&7] == 1);
+    assert!(profile.loops[&(4, 0)] == 2);
+
+    let text = profile.to_text(1);
+    assert!(text.starts_with("instructions: 7\nop codes:\n  ADD            3  42.86%\n"));
+    assert!(text.contains("hot loops:\n  0000-0004           2 iterations\n"));
+
+    let json = profile.to_json();
+    assert!(json.starts_with("{\"instructions\":7,\"op_codes\":{\"ADD\":3,\"JT\":3,\"HLT\":1}"));
+    assert!(json
+        .ends_with("\"blocks\":{\"0\":3,\"7\":1},\"loops\":[{\"from\":4,\"to\":0,\"count\":2}]}"));
+}
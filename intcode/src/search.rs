@@ -0,0 +1,250 @@
+// Searching for the inputs that make a program do something, e.g. the day 2
+// noun and verb:
+//
+//     let slots = [(Slot::Cell(1), 0..=99), (Slot::Cell(2), 0..=99)];
+//     let result = search::find_first(&program, &slots, |m| m.peek(0) == 19690720);
+//
+// Every combination of values for the slots is run on a fresh machine, in
+// order with the last slot changing fastest, spread over one worker thread
+// per core. The predicate only sees machines that halted, runs that fault,
+// wait for more input or do not halt within MAX_INSTRUCTIONS never match.
+// For straight-line programs symbolic::solve is much faster.
+use crate::mem_handler::{MemHandler, RunStatus};
+use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+// Instructions a single run may execute
+pub const MAX_INSTRUCTIONS: u64 = 10_000_000;
+
+// Where a value goes into the program
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Slot {
+    // Poked into memory at this address before running
+    Cell(usize),
+    // Queued as input, in the order of the slots
+    Input,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SearchResult {
+    // Values for the slots, in the order they were tried
+    pub assignments: Vec<Vec<i64>>,
+    // Number of combinations run
+    pub tried: u64,
+}
+
+// Results of f over every combination of values in ranges, in order, and
+// the number of combinations tried. The first range is split over the
+// workers. With first set only the first result is wanted, a worker gives up
+// as soon as it or an earlier one has a result.
+pub(crate) fn combinations<T, F>(ranges: &[RangeInclusive<i64>], first: bool, f: F) -> (Vec<T>, u64)
+where
+    T: Send,
+    F: Fn(&[i64]) -> Option<T> + Sync,
+{
+    if ranges.iter().any(|range| range.is_empty()) {
+        return (vec![], 0);
+    }
+    let outer = match ranges.first() {
+        Some(outer) => outer.clone(),
+        None => return (f(&[]).into_iter().collect(), 1),
+    };
+
+    let workers = thread::available_parallelism().map_or(1, |n| n.get());
+    let len = (*outer.end() as i128 - *outer.start() as i128 + 1) as u128;
+    let chunk_size = len.div_ceil(workers as u128) as i128;
+    let found = AtomicUsize::new(usize::MAX);
+
+    let parts: Vec<(Vec<T>, u64)> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|worker| {
+                let start = *outer.start() as i128 + worker as i128 * chunk_size;
+                let end = (start + chunk_size - 1).min(*outer.end() as i128);
+                let (f, found) = (&f, &found);
+                scope.spawn(move || {
+                    let mut results = vec![];
+                    let mut tried = 0;
+                    if start > end {
+                        return (results, tried);
+                    }
+                    let mut values: Vec<i64> = ranges.iter().map(|r| *r.start()).collect();
+                    values[0] = start as i64;
+                    loop {
+                        if first && found.load(Ordering::Relaxed) <= worker {
+                            return (results, tried);
+                        }
+                        tried += 1;
+                        if let Some(result) = f(&values) {
+                            results.push(result);
+                            found.fetch_min(worker, Ordering::Relaxed);
+                        }
+
+                        // Next combination, the last value changing fastest
+                        let mut var = values.len();
+                        loop {
+                            if var == 0 {
+                                return (results, tried);
+                            }
+                            var -= 1;
+                            let last = if var == 0 {
+                                end as i64
+                            } else {
+                                *ranges[var].end()
+                            };
+                            if values[var] < last {
+                                values[var] += 1;
+                                break;
+                            }
+                            values[var] = *ranges[var].start();
+                        }
+                    }
+                })
+            })
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+
+    let tried = parts.iter().map(|(_, tried)| tried).sum();
+    let mut results: Vec<T> = parts.into_iter().flat_map(|(results, _)| results).collect();
+    if first {
+        results.truncate(1);
+    }
+    (results, tried)
+}
+
+// First combination of values in ranges that passes test
+pub fn brute_force<F>(ranges: &[RangeInclusive<i64>], test: F) -> Option<Vec<i64>>
+where
+    F: Fn(&[i64]) -> bool + Sync,
+{
+    let (mut found, _) = combinations(ranges, true, |values| {
+        if test(values) {
+            Some(values.to_vec())
+        } else {
+            None
+        }
+    });
+    found.pop()
+}
+
+// The machine after running program with values in the slots, None unless
+// it halted
+pub(crate) fn run_with(
+    program: &[i64],
+    slots: &[(Slot, RangeInclusive<i64>)],
+    values: &[i64],
+) -> Option<MemHandler> {
+    let mut mem_handler = MemHandler::new(program.to_vec());
+    mem_handler.set_instruction_budget(MAX_INSTRUCTIONS);
+    mem_handler.detect_no_progress();
+    for ((slot, _), val) in slots.iter().zip(values) {
+        match slot {
+            Slot::Cell(address) => mem_handler.poke(*address, *val).ok()?,
+            Slot::Input => mem_handler.add_input(*val),
+        }
+    }
+
+    match mem_handler.run_to_halt() {
+        Ok(RunStatus::Halted) => Some(mem_handler),
+        _ => None,
+    }
+}
+
+fn find<P>(
+    program: &[i64],
+    slots: &[(Slot, RangeInclusive<i64>)],
+    first: bool,
+    predicate: P,
+) -> SearchResult
+where
+    P: Fn(&MemHandler) -> bool + Sync,
+{
+    let ranges: Vec<RangeInclusive<i64>> = slots.iter().map(|(_, range)| range.clone()).collect();
+    let (assignments, tried) = combinations(&ranges, first, |values| {
+        let mem_handler = run_with(program, slots, values)?;
+        if predicate(&mem_handler) {
+            Some(values.to_vec())
+        } else {
+            None
+        }
+    });
+    SearchResult { assignments, tried }
+}
+
+// The first assignment, in order, for which predicate holds on the halted
+// machine
+pub fn find_first<P>(
+    program: &[i64],
+    slots: &[(Slot, RangeInclusive<i64>)],
+    predicate: P,
+) -> SearchResult
+where
+    P: Fn(&MemHandler) -> bool + Sync,
+{
+    find(program, slots, true, predicate)
+}
+
+// Every assignment for which predicate holds on the halted machine
+pub fn find_all<P>(
+    program: &[i64],
+    slots: &[(Slot, RangeInclusive<i64>)],
+    predicate: P,
+) -> SearchResult
+where
+    P: Fn(&MemHandler) -> bool + Sync,
+{
+    find(program, slots, false, predicate)
+}
+
+#[test]
+fn test_combinations() {
+    let (found, tried) = combinations(&[0..=3, -1..=1], false, |values| Some(values.to_vec()));
+    assert!(tried == 12 && found.len() == 12);
+    assert!(found[0] == vec![0, -1] && found[1] == vec![0, 0] && found[11] == vec![3, 1]);
+
+    let empty = RangeInclusive::new(5, 4);
+    let (found, tried) = combinations(&[0..=3, empty], false, |values| Some(values.to_vec()));
+    assert!(found.is_empty() && tried == 0);
+    assert!(combinations(&[], false, |_| Some(1)) == (vec![1], 1));
+
+    let odd = |values: &[i64]| values[0] % 2 == 1 && values[1] == 0;
+    assert!(brute_force(&[0..=9, -5..=5], odd) == Some(vec![1, 0]));
+}
+
+#[test]
+fn test_find() {
+    // Outputs the product of its two inputs
+    let program = vec![3, 11, 3, 12, 2, 11, 12, 13, 4, 13, 99, 0, 0, 0];
+    let slots = [(Slot::Input, 1..=6), (Slot::Input, 1..=6)];
+    let six = |m: &MemHandler| m.last_result() == Some(6);
+
+    let result = find_all(&program, &slots, six);
+    assert!(result.assignments == vec![vec![1, 6], vec![2, 3], vec![3, 2], vec![6, 1]]);
+    assert!(result.tried == 36);
+
+    let result = find_first(&program, &slots, six);
+    assert!(result.assignments == vec![vec![1, 6]]);
+    assert!(result.tried >= 6 && result.tried <= 36);
+
+    // The multiply may also be patched into an add
+    let slots = [
+        (Slot::Cell(4), 1..=2),
+        (Slot::Input, 1..=6),
+        (Slot::Input, 1..=3),
+    ];
+    let result = find_all(&program, &slots, |m| m.peek(13) == 6);
+    assert!(result.assignments[..3] == [vec![1, 3, 3], vec![1, 4, 2], vec![1, 5, 1]]);
+    assert!(result.assignments[3..] == [vec![2, 2, 3], vec![2, 3, 2], vec![2, 6, 1]]);
+    assert!(result.tried == 36);
+
+    // Waiting for a third input is never a match
+    let slots = [(Slot::Input, 1..=6)];
+    assert!(
+        find_all(&program, &slots, |_| true)
+            == SearchResult {
+                assignments: vec![],
+                tried: 6
+            }
+    );
+}
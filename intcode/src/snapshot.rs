@@ -0,0 +1,190 @@
+// Full state of a MemHandler, taken with MemHandler::snapshot and put back
+// with MemHandler::restore. Snapshots can be saved to disk in a simple text
+// format, one field per line:
+//
+//     pc 12
+//     relative_base 5
+//     finished false
+//     input 1,2
+//     output 3
+//     memory 1101,1,2,3,...
+//     memory@4096 7,0,0,...
+//
+// Memory is written as one line per allocated block, blocks that do not
+// start at address 0 have their start address after an '@'. Reading such a
+// block fails when it takes memory beyond DEFAULT_MEMORY_LIMIT words.
+use crate::mem_handler::DEFAULT_MEMORY_LIMIT;
+use crate::memory::{DenseMemory, Memory};
+use crate::parser;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::str::FromStr;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Snapshot<M: Memory = DenseMemory> {
+    pub memory: M,
+    pub pc: usize,
+    pub relative_base: i64,
+    // Pending input in the order it will be read
+    pub input: Vec<M::Word>,
+    pub output: Vec<M::Word>,
+    pub finished: bool,
+}
+
+impl<M: Memory> Snapshot<M> {
+    pub fn save(&self, filename: &str) -> io::Result<()> {
+        fs::write(filename, self.to_string())
+    }
+
+    pub fn load(filename: &str) -> io::Result<Self> {
+        fs::read_to_string(filename)?
+            .parse()
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+}
+
+impl<M: Memory> fmt::Display for Snapshot<M> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "pc {}", self.pc)?;
+        writeln!(f, "relative_base {}", self.relative_base)?;
+        writeln!(f, "finished {}", self.finished)?;
+        writeln!(f, "input {}", parser::to_str(&self.input))?;
+        writeln!(f, "output {}", parser::to_str(&self.output))?;
+        for (start, words) in self.memory.blocks() {
+            if start == 0 {
+                writeln!(f, "memory {}", parser::to_str(words))?;
+            } else {
+                writeln!(f, "memory@{} {}", start, parser::to_str(words))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<M: Memory> FromStr for Snapshot<M> {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut snapshot = Snapshot {
+            memory: M::from(vec![]),
+            pc: 0,
+            relative_base: 0,
+            input: vec![],
+            output: vec![],
+            finished: false,
+        };
+
+        for line in text.lines().filter(|l| !l.trim().is_empty()) {
+            let bad = || format!("bad snapshot line '{}'", line);
+            let mut parts = line.splitn(2, ' ');
+            let key = parts.next().unwrap_or("");
+            let value = parts.next().unwrap_or("").trim();
+            let words = || -> Result<Vec<M::Word>, String> {
+                value
+                    .split(',')
+                    .filter(|w| !w.is_empty())
+                    .map(|w| w.trim().parse::<M::Word>().map_err(|_| bad()))
+                    .collect()
+            };
+
+            match key {
+                "pc" => snapshot.pc = value.parse().map_err(|_| bad())?,
+                "relative_base" => snapshot.relative_base = value.parse().map_err(|_| bad())?,
+                "finished" => snapshot.finished = value.parse().map_err(|_| bad())?,
+                "input" => snapshot.input = words()?,
+                "output" => snapshot.output = words()?,
+                "memory" => snapshot.memory = M::from(words()?),
+                _ => match key.strip_prefix("memory@") {
+                    Some(start) => {
+                        let start: usize = start.parse().map_err(|_| bad())?;
+                        for (offset, word) in words()?.into_iter().enumerate() {
+                            // Held to the default memory limit of MemHandler,
+                            // a snapshot is no reason to allocate more
+                            let address = start.checked_add(offset).ok_or_else(bad)?;
+                            match snapshot.memory.allocated_with(address) {
+                                Some(words) if words <= DEFAULT_MEMORY_LIMIT => {}
+                                _ => return Err(format!("memory beyond the limit in '{}'", line)),
+                            }
+                            snapshot.memory.set(address, word);
+                        }
+                    }
+                    None => return Err(bad()),
+                },
+            }
+        }
+
+        Ok(snapshot)
+    }
+}
+
+#[test]
+fn test_fork() {
+    use crate::mem_handler::{MemHandler, RunStatus};
+
+    // Outputs input + 1 for every input
+    let program = vec![3, 11, 101, 1, 11, 11, 4, 11, 1105, 1, 0, 0];
+    let mut mem_handler = MemHandler::new(program);
+    mem_handler.add_input(1);
+    assert!(mem_handler.run_until_event() == Ok(RunStatus::Output(2)));
+
+    mem_handler.add_input(10);
+    let snapshot = mem_handler.snapshot();
+    assert!(snapshot.input == vec![10] && snapshot.output == vec![2]);
+
+    assert!(mem_handler.run_until_event() == Ok(RunStatus::Output(11)));
+    mem_handler.restore(&snapshot);
+    assert!(mem_handler.pending_input() == vec![10]);
+    assert!(mem_handler.run_until_event() == Ok(RunStatus::Output(11)));
+    assert!(mem_handler.get_result() == Some(vec![2, 11]));
+
+    let mut fork = MemHandler::from_snapshot(&snapshot);
+    assert!(fork.run_until_event() == Ok(RunStatus::Output(11)));
+    assert!(fork.snapshot() == mem_handler.snapshot());
+}
+
+#[test]
+fn test_text_format() {
+    let snapshot = Snapshot {
+        memory: DenseMemory::from(vec![1101, -1, 2, 3, 99]),
+        pc: 4,
+        relative_base: -5,
+        input: vec![],
+        output: vec![7, 8],
+        finished: true,
+    };
+    let text = snapshot.to_string();
+    assert!(text.contains("\ninput \n") && text.contains("\noutput 7,8\n"));
+    assert!(text.parse::<Snapshot>() == Ok(snapshot));
+    assert!("pc x".parse::<Snapshot>().is_err());
+    assert!("registers 1".parse::<Snapshot>().is_err());
+}
+
+#[test]
+fn test_paged_text_format() {
+    use crate::memory::{PagedMemory, PAGE_SIZE};
+
+    let mut memory = PagedMemory::from(vec![1, 2, 3]);
+    memory.set(5 * PAGE_SIZE + 1, 4);
+    let snapshot = Snapshot {
+        memory,
+        pc: 0,
+        relative_base: 0,
+        input: vec![],
+        output: vec![],
+        finished: false,
+    };
+    let text = snapshot.to_string();
+    assert!(text.contains(&format!("\nmemory@{} 0,4,0,", 5 * PAGE_SIZE)));
+    assert!(text.parse::<Snapshot<PagedMemory>>() == Ok(snapshot));
+
+    // Far away memory only fits in pages, and no memory fits beyond usize
+    let far = "memory@1000000000000 1";
+    assert!(far.parse::<Snapshot<PagedMemory>>().is_ok());
+    assert!(far.parse::<Snapshot>() == Err(format!("memory beyond the limit in '{}'", far)));
+    let beyond = format!("memory@{} 7,8", usize::MAX);
+    assert!(
+        beyond.parse::<Snapshot<PagedMemory>>() == Err(format!("bad snapshot line '{}'", beyond))
+    );
+    assert!("memory@x 1".parse::<Snapshot>().is_err());
+}
@@ -0,0 +1,426 @@
+// Solving for the inputs of a program, e.g. the noun and verb of day 2 that
+// make the program leave 19690720 at address 0:
+//
+//     let inputs = [(1, 0..=99), (2, 0..=99)];
+//     let values = symbolic::solve(&program, &inputs, 0, 19690720);
+//
+// Straight-line programs, which run from address 0 to their exit without
+// jumps or I/O, are executed symbolically. The input cells are variables and
+// every cell ends up as a Polynomial in them, which is solved for the target
+// directly. The answer is then run once on a MemHandler, which also sees
+// faults and wrapping arithmetic. Anything else, or an answer that does not
+// hold there, falls back to trying every combination of values, see search.
+use crate::error::VmError;
+use crate::mem_handler::{MemHandler, DEFAULT_MEMORY_LIMIT};
+use crate::op_code::{decode, OpCode, ParamMode};
+use crate::search::{self, Slot};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::RangeInclusive;
+
+// Integer polynomial in the variables x0, x1, ...
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Polynomial {
+    // Coefficient per monomial, a monomial being the sorted list of the
+    // variables multiplied in it. Coefficients are never 0.
+    terms: BTreeMap<Vec<usize>, i64>,
+}
+
+impl Polynomial {
+    pub fn constant(val: i64) -> Self {
+        let mut polynomial = Polynomial::default();
+        if val != 0 {
+            polynomial.terms.insert(vec![], val);
+        }
+        polynomial
+    }
+
+    pub fn variable(var: usize) -> Self {
+        let mut polynomial = Polynomial::default();
+        polynomial.terms.insert(vec![var], 1);
+        polynomial
+    }
+
+    // The value, if the polynomial does not depend on any variable
+    pub fn as_constant(&self) -> Option<i64> {
+        match self.terms.iter().next() {
+            None => Some(0),
+            Some((monomial, val)) if monomial.is_empty() && self.terms.len() == 1 => Some(*val),
+            _ => None,
+        }
+    }
+
+    fn add_term(&mut self, monomial: Vec<usize>, val: i64) -> Option<()> {
+        let sum = self.terms.get(&monomial).unwrap_or(&0).checked_add(val)?;
+        if sum == 0 {
+            self.terms.remove(&monomial);
+        } else {
+            self.terms.insert(monomial, sum);
+        }
+        Some(())
+    }
+
+    // None on overflow
+    pub fn add(&self, other: &Polynomial) -> Option<Polynomial> {
+        let mut sum = self.clone();
+        for (monomial, val) in other.terms.iter() {
+            sum.add_term(monomial.clone(), *val)?;
+        }
+        Some(sum)
+    }
+
+    pub fn multiply(&self, other: &Polynomial) -> Option<Polynomial> {
+        let mut product = Polynomial::default();
+        for (a, x) in self.terms.iter() {
+            for (b, y) in other.terms.iter() {
+                let mut monomial: Vec<usize> = a.iter().chain(b.iter()).cloned().collect();
+                monomial.sort_unstable();
+                product.add_term(monomial, x.checked_mul(*y)?)?;
+            }
+        }
+        Some(product)
+    }
+
+    // Value with xi = values[i], None on overflow
+    pub fn evaluate(&self, values: &[i64]) -> Option<i64> {
+        let mut sum: i64 = 0;
+        for (monomial, val) in self.terms.iter() {
+            let mut term = *val;
+            for var in monomial {
+                term = term.checked_mul(values[*var])?;
+            }
+            sum = sum.checked_add(term)?;
+        }
+        Some(sum)
+    }
+
+    // Values for the variables, each within its range, for which the
+    // polynomial equals target. A variable that is never multiplied by
+    // itself is solved for with a division, the others are tried one by one.
+    pub fn solve(&self, ranges: &[RangeInclusive<i64>], target: i64) -> Option<Vec<i64>> {
+        let linear = (0..ranges.len()).rev().find(|var| {
+            self.terms
+                .keys()
+                .all(|monomial| monomial.iter().filter(|v| *v == var).count() <= 1)
+        });
+        let linear = match linear {
+            Some(linear) => linear,
+            None => {
+                return search::brute_force(ranges, |values| self.evaluate(values) == Some(target))
+            }
+        };
+
+        // The polynomial is a * x + b, with a and b in the other variables
+        let mut a = Polynomial::default();
+        let mut b = Polynomial::default();
+        for (monomial, val) in self.terms.iter() {
+            if monomial.contains(&linear) {
+                let rest = monomial.iter().filter(|v| **v != linear).cloned().collect();
+                a.terms.insert(rest, *val);
+            } else {
+                b.terms.insert(monomial.clone(), *val);
+            }
+        }
+
+        let range = ranges[linear].clone();
+        let mut others = ranges.to_vec();
+        others[linear] = 0..=0;
+        let (mut found, _) = search::combinations(&others, true, |values| {
+            let (a, b) = (a.evaluate(values)?, b.evaluate(values)?);
+            let rest = target.checked_sub(b)?;
+            let x = match a {
+                0 if rest == 0 => *range.start(),
+                0 => return None,
+                _ if rest % a != 0 => return None,
+                _ => rest / a,
+            };
+            if !range.contains(&x) {
+                return None;
+            }
+            let mut values = values.to_vec();
+            values[linear] = x;
+            Some(values)
+        });
+        found.pop()
+    }
+}
+
+impl fmt::Display for Polynomial {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.terms.is_empty() {
+            return write!(f, "0");
+        }
+
+        for (index, (monomial, val)) in self.terms.iter().enumerate() {
+            match (index, *val < 0) {
+                (0, true) => write!(f, "-")?,
+                (0, false) => {}
+                (_, true) => write!(f, " - ")?,
+                (_, false) => write!(f, " + ")?,
+            }
+            let val = val.unsigned_abs();
+            let vars: Vec<String> = monomial.iter().map(|var| format!("x{}", var)).collect();
+            match (val, vars.is_empty()) {
+                (_, true) => write!(f, "{}", val)?,
+                (1, false) => write!(f, "{}", vars.join("*"))?,
+                (_, false) => write!(f, "{}*{}", val, vars.join("*"))?,
+            }
+        }
+        Ok(())
+    }
+}
+
+// Why a program could not be executed symbolically
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Unsupported {
+    // A jump, input or output at address
+    NotStraightLine { address: usize },
+    // The instruction at address, or the address it writes to, depends on
+    // the variables
+    SymbolicAddress { address: usize },
+    // The cell depends on a comparison of variables, or on memory read
+    // through an address that depends on them
+    Unknown { cell: usize },
+    Fault(VmError),
+}
+
+impl fmt::Display for Unsupported {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Unsupported::NotStraightLine { address } => {
+                write!(f, "jump or I/O at address {}", address)
+            }
+            Unsupported::SymbolicAddress { address } => {
+                write!(f, "address depends on the inputs at address {}", address)
+            }
+            Unsupported::Unknown { cell } => write!(f, "cell {} can not be expressed", cell),
+            Unsupported::Fault(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+// Memory of the symbolic machine, None for values that are not polynomials
+struct Machine {
+    memory: Vec<Option<Polynomial>>,
+    pc: usize,
+    relative_base: i64,
+}
+
+impl Machine {
+    fn get(&self, address: usize) -> Option<Polynomial> {
+        match self.memory.get(address) {
+            Some(val) => val.clone(),
+            None => Some(Polynomial::default()),
+        }
+    }
+
+    fn set(&mut self, address: usize, val: Option<Polynomial>) -> Result<(), Unsupported> {
+        if address >= DEFAULT_MEMORY_LIMIT {
+            return Err(Unsupported::Fault(VmError::OutOfMemory {
+                address: self.pc,
+                position: address,
+            }));
+        }
+        if address >= self.memory.len() {
+            self.memory.resize(address + 1, Some(Polynomial::default()));
+        }
+        self.memory[address] = val;
+        Ok(())
+    }
+
+    // Address given by parameter number offset, None if it depends on the
+    // variables
+    fn position(&self, offset: usize, mode: ParamMode) -> Result<Option<usize>, Unsupported> {
+        let word = match self.get(self.pc + offset).and_then(|p| p.as_constant()) {
+            Some(word) => word,
+            None => return Ok(None),
+        };
+        let position = match mode {
+            ParamMode::Relative => word
+                .checked_add(self.relative_base)
+                .ok_or(Unsupported::Fault(VmError::Overflow { address: self.pc }))?,
+            _ => word,
+        };
+        if position < 0 {
+            return Err(Unsupported::Fault(VmError::NegativeAddress {
+                address: self.pc,
+                position,
+            }));
+        }
+        Ok(Some(position as usize))
+    }
+
+    fn parameter(&self, offset: usize, mode: ParamMode) -> Result<Option<Polynomial>, Unsupported> {
+        match mode {
+            ParamMode::Immediate => Ok(self.get(self.pc + offset)),
+            _ => Ok(self.position(offset, mode)?.and_then(|p| self.get(p))),
+        }
+    }
+
+    fn target(&self, offset: usize, mode: ParamMode) -> Result<usize, Unsupported> {
+        if mode == ParamMode::Immediate {
+            return Err(Unsupported::Fault(VmError::WriteToImmediate {
+                address: self.pc,
+            }));
+        }
+        self.position(offset, mode)?
+            .ok_or(Unsupported::SymbolicAddress { address: self.pc })
+    }
+
+    fn run(&mut self) -> Result<(), Unsupported> {
+        loop {
+            let pc = self.pc;
+            let instruction = self
+                .get(pc)
+                .and_then(|p| p.as_constant())
+                .ok_or(Unsupported::SymbolicAddress { address: pc })?;
+            let (op_code, modes) = decode(pc, instruction).map_err(Unsupported::Fault)?;
+            let overflow = Unsupported::Fault(VmError::Overflow { address: pc });
+
+            match op_code {
+                OpCode::Add | OpCode::Multiply | OpCode::LessThan | OpCode::Equal => {
+                    let a = self.parameter(1, modes[0])?;
+                    let b = self.parameter(2, modes[1])?;
+                    let to = self.target(3, modes[2])?;
+                    let val = match (a, b) {
+                        (Some(a), Some(b)) => match op_code {
+                            OpCode::Add => Some(a.add(&b).ok_or(overflow)?),
+                            OpCode::Multiply => Some(a.multiply(&b).ok_or(overflow)?),
+                            _ => match (a.as_constant(), b.as_constant()) {
+                                (Some(a), Some(b)) => {
+                                    let holds = (op_code == OpCode::LessThan && a < b)
+                                        || (op_code == OpCode::Equal && a == b);
+                                    Some(Polynomial::constant(holds as i64))
+                                }
+                                _ => None,
+                            },
+                        },
+                        _ => None,
+                    };
+                    self.set(to, val)?;
+                    self.pc += 4;
+                }
+                OpCode::ChangeRelative => {
+                    let offset = self
+                        .parameter(1, modes[0])?
+                        .and_then(|p| p.as_constant())
+                        .ok_or(Unsupported::SymbolicAddress { address: pc })?;
+                    self.relative_base = self
+                        .relative_base
+                        .checked_add(offset)
+                        .ok_or(Unsupported::Fault(VmError::Overflow { address: pc }))?;
+                    self.pc += 2;
+                }
+                OpCode::Exit => return Ok(()),
+                _ => return Err(Unsupported::NotStraightLine { address: pc }),
+            }
+        }
+    }
+}
+
+// The value the program leaves in cell, as a polynomial in the cells at the
+// addresses given by variables, i.e. x0 is the cell at variables[0]
+pub fn expression(
+    program: &[i64],
+    variables: &[usize],
+    cell: usize,
+) -> Result<Polynomial, Unsupported> {
+    let mut machine = Machine {
+        memory: program
+            .iter()
+            .map(|word| Some(Polynomial::constant(*word)))
+            .collect(),
+        pc: 0,
+        relative_base: 0,
+    };
+    for (var, address) in variables.iter().enumerate() {
+        machine.set(*address, Some(Polynomial::variable(var)))?;
+    }
+
+    machine.run()?;
+    machine.get(cell).ok_or(Unsupported::Unknown { cell })
+}
+
+// Values for the cells at the addresses of inputs, each within its range,
+// making the program halt with target in cell
+pub fn solve(
+    program: &[i64],
+    inputs: &[(usize, RangeInclusive<i64>)],
+    cell: usize,
+    target: i64,
+) -> Option<Vec<i64>> {
+    let slots: Vec<(Slot, RangeInclusive<i64>)> = inputs
+        .iter()
+        .map(|(address, range)| (Slot::Cell(*address), range.clone()))
+        .collect();
+    let holds = |m: &MemHandler| m.peek(cell) == target;
+
+    // The polynomial does not see faults through addresses depending on
+    // the variables, nor wrapping arithmetic, so its answer is checked on
+    // the real machine first
+    let addresses: Vec<usize> = inputs.iter().map(|(address, _)| *address).collect();
+    let ranges: Vec<RangeInclusive<i64>> = inputs.iter().map(|(_, range)| range.clone()).collect();
+    if let Ok(expression) = expression(program, &addresses, cell) {
+        if let Some(values) = expression.solve(&ranges, target) {
+            if search::run_with(program, &slots, &values).is_some_and(|m| holds(&m)) {
+                return Some(values);
+            }
+        }
+    }
+
+    search::find_first(program, &slots, holds).assignments.pop()
+}
+
+#[test]
+fn test_polynomial() {
+    let x = Polynomial::variable(0);
+    let y = Polynomial::variable(1);
+    let p = x
+        .multiply(&x)
+        .unwrap()
+        .add(&Polynomial::constant(-3))
+        .unwrap();
+    let p = p
+        .add(&y.multiply(&Polynomial::constant(-2)).unwrap())
+        .unwrap();
+    assert!(p.to_string() == "-3 + x0*x0 - 2*x1");
+    assert!(p.evaluate(&[4, 5]) == Some(3));
+    assert!(p.add(&p.multiply(&Polynomial::constant(-1)).unwrap()) == Some(Polynomial::default()));
+    assert!(Polynomial::constant(7).as_constant() == Some(7) && p.as_constant().is_none());
+
+    // x1 is solved for, x0 tried
+    assert!(p.solve(&[0..=10, 0..=10], 3) == Some(vec![4, 5]));
+    assert!(p.solve(&[0..=10, 0..=10], 6) == Some(vec![3, 0]));
+    assert!(p.solve(&[0..=10, 0..=10], 200).is_none());
+    assert!(x.multiply(&x).unwrap().solve(&[-10..=10], 49) == Some(vec![-7]));
+}
+
+#[test]
+fn test_expression() {
+    // Reads through the variable addresses first, then sets memory[0] to
+    // x0 * x1 + x0
+    let program = vec![1, 0, 0, 14, 2, 1, 2, 13, 1, 13, 1, 0, 99, 0, 0];
+    let polynomial = expression(&program, &[1, 2], 0).unwrap();
+    assert!(polynomial.to_string() == "x0 + x0*x1");
+    assert!(expression(&program, &[1, 2], 14) == Err(Unsupported::Unknown { cell: 14 }));
+    assert!(solve(&program, &[(1, 0..=99), (2, 0..=99)], 0, 60) == Some(vec![1, 59]));
+
+    // Outputs memory[0] + memory[x0], so it can only be brute forced
+    let program = vec![1, 0, 0, 0, 4, 0, 99];
+    assert!(expression(&program, &[1], 0) == Err(Unsupported::NotStraightLine { address: 4 }));
+    assert!(solve(&program, &[(1, 0..=10)], 0, 5) == Some(vec![4]));
+    assert!(solve(&program, &[(1, 0..=10)], 0, 7).is_none());
+
+    // Reads through x0 and x1, the polynomial x0 + x1 misses that negative
+    // addresses fault
+    let program = [1, 0, 0, 3, 1, 1, 2, 0, 99];
+    let inputs = [(1, -5..=5), (2, -5..=5)];
+    assert!(expression(&program, &[1, 2], 0).unwrap().to_string() == "x0 + x1");
+    assert!(solve(&program, &inputs, 0, -8).is_none());
+    assert!(solve(&program, &inputs, 0, 8) == Some(vec![3, 5]));
+
+    // The relative base overflows, adjusted or as part of an address
+    let overflow = Err(Unsupported::Fault(VmError::Overflow { address: 2 }));
+    assert!(expression(&[109, i64::MAX, 109, 1, 99], &[], 0) == overflow);
+    assert!(expression(&[109, i64::MAX, 1201, 1, 0, 0, 99], &[], 0) == overflow);
+}
@@ -0,0 +1,261 @@
+// Execution traces. When tracing is enabled on a MemHandler every executed
+// instruction is recorded as an entry, written one per line as:
+//
+//     <pc> <MNEMONIC> [operands] [i<val>] [w<addr>=<val>] [r<base>] [o<val>]
+//
+// e.g. '8 ADD 3,5,100 w100=8' or '12 IN 13 i4 w13=4'. Operands are the
+// values read by the instruction, and the resolved address for parameters
+// that are written to. The other fields are memory writes, the new relative
+// base and the value read as input or produced as output.
+//
+// Entries hold words of the type the machine runs with, i64 by default.
+//
+// A trace can be replayed with verify, which runs the program again, feeding
+// it the inputs found in the trace, and reports the first entry that differs.
+use crate::error::VmError;
+use crate::mem_handler::{MemHandler, RunStatus};
+use crate::op_code::OpCode;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::str::FromStr;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TraceEntry<W = i64> {
+    pub pc: usize,
+    pub op_code: OpCode,
+    pub operands: Vec<W>,
+    pub writes: Vec<(usize, W)>,
+    pub relative_base: Option<i64>,
+    pub input: Option<W>,
+    pub output: Option<W>,
+}
+
+impl<W> TraceEntry<W> {
+    pub fn new(pc: usize, op_code: OpCode) -> Self {
+        TraceEntry {
+            pc,
+            op_code,
+            operands: vec![],
+            writes: vec![],
+            relative_base: None,
+            input: None,
+            output: None,
+        }
+    }
+}
+
+impl<W: fmt::Display> fmt::Display for TraceEntry<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let operands: Vec<String> = self.operands.iter().map(|o| o.to_string()).collect();
+        write!(f, "{} {}", self.pc, self.op_code.mnemonic())?;
+        if !operands.is_empty() {
+            write!(f, " {}", operands.join(","))?;
+        }
+
+        if let Some(val) = &self.input {
+            write!(f, " i{}", val)?;
+        }
+        for (address, val) in self.writes.iter() {
+            write!(f, " w{}={}", address, val)?;
+        }
+        if let Some(base) = self.relative_base {
+            write!(f, " r{}", base)?;
+        }
+        if let Some(val) = &self.output {
+            write!(f, " o{}", val)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<W: FromStr> FromStr for TraceEntry<W> {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let bad = || format!("bad trace entry '{}'", line);
+        let number = |s: &str| s.parse::<W>().map_err(|_| bad());
+
+        let mut fields = line.split(' ');
+        let pc = fields.next().and_then(|f| f.parse().ok()).ok_or_else(bad)?;
+        let op_code = fields
+            .next()
+            .and_then(OpCode::from_mnemonic)
+            .ok_or_else(bad)?;
+
+        let mut entry = TraceEntry::new(pc, op_code);
+        if op_code.nr_of_params() > 0 {
+            let operands = fields.next().ok_or_else(bad)?;
+            for operand in operands.split(',') {
+                entry.operands.push(number(operand)?);
+            }
+        }
+
+        for field in fields {
+            let (kind, value) = field.split_at(1.min(field.len()));
+            match kind {
+                "i" => entry.input = Some(number(value)?),
+                "r" => entry.relative_base = Some(value.parse().map_err(|_| bad())?),
+                "o" => entry.output = Some(number(value)?),
+                "w" => {
+                    let mut parts = value.split('=');
+                    let address = parts.next().and_then(|a| a.parse().ok()).ok_or_else(bad)?;
+                    let val = number(parts.next().ok_or_else(bad)?)?;
+                    entry.writes.push((address, val));
+                }
+                _ => return Err(bad()),
+            }
+        }
+
+        Ok(entry)
+    }
+}
+
+pub fn to_string(trace: &[TraceEntry]) -> String {
+    trace.iter().map(|entry| format!("{}\n", entry)).collect()
+}
+
+pub fn from_str(text: &str) -> Result<Vec<TraceEntry>, String> {
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.parse())
+        .collect()
+}
+
+pub fn write_file(filename: &str, trace: &[TraceEntry]) -> io::Result<()> {
+    fs::write(filename, to_string(trace))
+}
+
+pub fn read_file(filename: &str) -> io::Result<Vec<TraceEntry>> {
+    let text = fs::read_to_string(filename)?;
+    from_str(&text).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+// Runs the program with tracing enabled until it halts, needs input that is
+// not given or has executed max_steps instructions
+pub fn record(
+    program: Vec<i64>,
+    input: &[i64],
+    max_steps: usize,
+) -> Result<Vec<TraceEntry>, VmError> {
+    let mut mem_handler = traced(program, input);
+    run_traced(&mut mem_handler, max_steps)?;
+    Ok(mem_handler.take_trace())
+}
+
+fn traced(program: Vec<i64>, input: &[i64]) -> MemHandler {
+    let mut mem_handler = MemHandler::new(program);
+    mem_handler.enable_trace();
+    for val in input {
+        mem_handler.add_input(*val);
+    }
+    mem_handler
+}
+
+fn run_traced(mem_handler: &mut MemHandler, max_steps: usize) -> Result<(), VmError> {
+    for _ in 0..max_steps {
+        match mem_handler.run()? {
+            RunStatus::Stepped | RunStatus::Output(_) => {}
+            _ => break,
+        }
+    }
+    Ok(())
+}
+
+// First place where a fresh run differs from the expected trace. A missing
+// entry means that one of the runs stopped early.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Divergence {
+    pub index: usize,
+    pub expected: Option<TraceEntry>,
+    pub actual: Option<TraceEntry>,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let show = |entry: &Option<TraceEntry>| match entry {
+            Some(entry) => entry.to_string(),
+            None => "<end of trace>".to_string(),
+        };
+        write!(
+            f,
+            "trace differs at step {}\n  expected: {}\n  actual:   {}",
+            self.index,
+            show(&self.expected),
+            show(&self.actual)
+        )
+    }
+}
+
+// Runs the program again with the inputs recorded in the trace and checks
+// that it executes exactly the same instructions
+pub fn verify(program: Vec<i64>, expected: &[TraceEntry]) -> Result<(), Box<Divergence>> {
+    let input: Vec<i64> = expected.iter().filter_map(|entry| entry.input).collect();
+
+    // One extra step to notice a run that keeps going past the trace. A
+    // fault simply ends the run, the faulting instruction is not recorded.
+    let mut mem_handler = traced(program, &input);
+    let _ = run_traced(&mut mem_handler, expected.len() + 1);
+    let actual = mem_handler.take_trace();
+
+    for index in 0..expected.len().max(actual.len()) {
+        if expected.get(index) != actual.get(index) {
+            return Err(Box::new(Divergence {
+                index,
+                expected: expected.get(index).cloned(),
+                actual: actual.get(index).cloned(),
+            }));
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_entry_format() {
+    let text = "12 IN 13 i4 w13=4";
+    let entry: TraceEntry = text.parse().unwrap();
+    assert!(entry.pc == 12 && entry.op_code == OpCode::Input);
+    assert!(entry.operands == vec![13] && entry.writes == vec![(13, 4)]);
+    assert!(entry.input == Some(4));
+    assert!(entry.to_string() == text);
+
+    let text = "0 HLT";
+    assert!(text.parse::<TraceEntry>().unwrap().to_string() == text);
+    assert!("0 FOO 1".parse::<TraceEntry>().is_err());
+    assert!("0 OUT 1 x2".parse::<TraceEntry>().is_err());
+}
+
+#[test]
+fn test_record() {
+    // Reads a value, doubles it, outputs it and moves the relative base
+    let program = vec![3, 11, 1002, 11, 2, 11, 4, 11, 109, 3, 99, 0];
+    let trace = record(program, &[21], 100).unwrap();
+
+    let expected = "0 IN 11 i21 w11=21\n\
+                    2 MUL 21,2,11 w11=42\n\
+                    6 OUT 42 o42\n\
+                    8 ARB 3 r3\n\
+                    10 HLT\n";
+    assert!(to_string(&trace) == expected);
+    assert!(from_str(expected) == Ok(trace));
+}
+
+#[test]
+fn test_verify() {
+    let program = vec![3, 11, 1002, 11, 2, 11, 4, 11, 109, 3, 99, 0];
+    let trace = record(program.clone(), &[21], 100).unwrap();
+    assert!(verify(program.clone(), &trace) == Ok(()));
+
+    // Multiply by 3 instead
+    let mut changed = program.clone();
+    changed[4] = 3;
+    let divergence = verify(changed, &trace).unwrap_err();
+    assert!(divergence.index == 1);
+    assert!(divergence.actual.unwrap().writes == vec![(11, 63)]);
+
+    // Ends early
+    let divergence = verify(program, &trace[..3]).unwrap_err();
+    assert!(divergence.index == 3 && divergence.expected.is_none());
+}
@@ -0,0 +1,386 @@
+// Translates a program into a Rust module with one `match pc` arm per
+// instruction, for programs that are run so often that the interpreter is
+// the bottleneck. The generated struct has the same interface as MemHandler
+// for running the program, feeding it input and reading output and memory,
+// and depends on the intcode crate for RunStatus and VmError. Arithmetic
+// wraps, like the default of MemHandler.
+//
+// Every address of the program that decodes into an instruction gets an arm,
+// since jump targets are often only known at run time. The arm runs as long
+// as the address still holds the word it was generated from. Op codes and
+// parameter modes are fixed in the generated code while parameters are read
+// from memory, so patching parameters, like the day 2 noun and verb, is
+// fine. Running a modified instruction, or any address outside the program,
+// hands the machine over to a MemHandler that continues from the same state.
+use crate::disasm::{self, Instruction};
+use crate::op_code::{OpCode, ParamMode};
+use std::fmt::Write;
+
+// Everything except the program and its dispatch loop, NAME is replaced by
+// the name of the struct
+const RUNTIME: &str = r#"
+pub struct NAME {
+    memory: Vec<i64>,
+    pc: usize,
+    relative_base: i64,
+    input: VecDeque<i64>,
+    output: Vec<i64>,
+    finished: bool,
+    interpreter: Option<MemHandler>,
+}
+
+impl Default for NAME {
+    fn default() -> Self {
+        NAME::new()
+    }
+}
+
+impl NAME {
+    pub fn new() -> Self {
+        NAME {
+            memory: PROGRAM.to_vec(),
+            pc: 0,
+            relative_base: 0,
+            input: VecDeque::new(),
+            output: vec![],
+            finished: false,
+            interpreter: None,
+        }
+    }
+
+    pub fn add_input(&mut self, input: i64) {
+        match &mut self.interpreter {
+            Some(interpreter) => interpreter.add_input(input),
+            None => self.input.push_back(input),
+        }
+    }
+
+    pub fn get_result(&self) -> Option<Vec<i64>> {
+        match &self.interpreter {
+            Some(interpreter) => interpreter.get_result(),
+            None if self.output.is_empty() => None,
+            None => Some(self.output.clone()),
+        }
+    }
+
+    pub fn last_result(&self) -> Option<i64> {
+        match &self.interpreter {
+            Some(interpreter) => interpreter.last_result(),
+            None => self.output.last().cloned(),
+        }
+    }
+
+    pub fn finished(&self) -> bool {
+        match &self.interpreter {
+            Some(interpreter) => interpreter.finished(),
+            None => self.finished,
+        }
+    }
+
+    pub fn peek(&self, address: usize) -> i64 {
+        match &self.interpreter {
+            Some(interpreter) => interpreter.peek(address),
+            None => self.get(address),
+        }
+    }
+
+    pub fn poke(&mut self, address: usize, val: i64) -> Result<(), VmError> {
+        match &mut self.interpreter {
+            Some(interpreter) => interpreter.poke(address, val),
+            None => self.set(address, val),
+        }
+    }
+
+    pub fn memory(&self) -> &[i64] {
+        match &self.interpreter {
+            Some(interpreter) => interpreter.memory(),
+            None => &self.memory,
+        }
+    }
+
+    pub fn pc(&self) -> usize {
+        match &self.interpreter {
+            Some(interpreter) => interpreter.pc(),
+            None => self.pc,
+        }
+    }
+
+    // True once the program has been handed over to the interpreter
+    pub fn interpreted(&self) -> bool {
+        self.interpreter.is_some()
+    }
+
+    pub fn run_to_halt(&mut self) -> Result<RunStatus, VmError> {
+        loop {
+            match self.run_until_event()? {
+                RunStatus::Output(_) => continue,
+                status => return Ok(status),
+            }
+        }
+    }
+
+    fn get(&self, address: usize) -> i64 {
+        self.memory.get(address).cloned().unwrap_or(0)
+    }
+
+    fn set(&mut self, address: usize, val: i64) -> Result<(), VmError> {
+        if address >= DEFAULT_MEMORY_LIMIT {
+            return Err(VmError::OutOfMemory {
+                address: self.pc,
+                position: address,
+            });
+        }
+        if address >= self.memory.len() {
+            self.memory.resize(address + 1, 0);
+        }
+        self.memory[address] = val;
+        Ok(())
+    }
+
+    fn to_position(&self, position: i64) -> Result<usize, VmError> {
+        if position < 0 {
+            return Err(VmError::NegativeAddress {
+                address: self.pc,
+                position,
+            });
+        }
+        Ok(position as usize)
+    }
+
+    // Address given by the position mode parameter at address
+    fn position(&self, address: usize) -> Result<usize, VmError> {
+        self.to_position(self.get(address))
+    }
+
+    // Address given by the relative mode parameter at address, one beyond
+    // any i64 is reported like MemHandler does
+    fn relative(&self, address: usize) -> Result<usize, VmError> {
+        let word = self.get(address);
+        match word.checked_add(self.relative_base) {
+            Some(position) => self.to_position(position),
+            None if word < 0 => Err(VmError::NegativeAddress {
+                address: self.pc,
+                position: i64::MIN,
+            }),
+            None => Err(VmError::OutOfMemory {
+                address: self.pc,
+                position: usize::MAX,
+            }),
+        }
+    }
+
+    fn start_interpreter(&mut self) {
+        let snapshot = Snapshot {
+            memory: DenseMemory::from(std::mem::take(&mut self.memory)),
+            pc: self.pc,
+            relative_base: self.relative_base,
+            input: self.input.drain(..).collect(),
+            output: std::mem::take(&mut self.output),
+            finished: self.finished,
+        };
+        self.interpreter = Some(MemHandler::from_snapshot(&snapshot));
+    }
+"#;
+
+// Instructions the program starts out with, by address
+fn instructions(program: &[i64]) -> Vec<(usize, Instruction)> {
+    (0..program.len())
+        .filter_map(|address| Some((address, disasm::decode_at(program, address)?)))
+        .filter(|(_, instruction)| {
+            // Left to the interpreter, which reports the fault
+            let params = &instruction.params;
+            !(instruction.op_code.writes() && params.last().unwrap().mode == ParamMode::Immediate)
+        })
+        .collect()
+}
+
+// Expression reading parameter number param of the instruction at address
+fn read(address: usize, param: usize, mode: ParamMode) -> String {
+    let at = address + param + 1;
+    match mode {
+        ParamMode::Position => format!("self.get(self.position({})?)", at),
+        ParamMode::Immediate => format!("self.get({})", at),
+        ParamMode::Relative => format!("self.get(self.relative({})?)", at),
+    }
+}
+
+// Expression giving the address written by parameter number param
+fn target(address: usize, param: usize, mode: ParamMode) -> String {
+    let at = address + param + 1;
+    match mode {
+        ParamMode::Relative => format!("self.relative({})?", at),
+        _ => format!("self.position({})?", at),
+    }
+}
+
+fn arm(out: &mut String, address: usize, word: i64, instruction: &Instruction) {
+    let mode = |param: usize| instruction.params[param].mode;
+    let next = address + instruction.params.len() + 1;
+    let mut body = vec![];
+
+    match instruction.op_code {
+        OpCode::Add | OpCode::Multiply | OpCode::LessThan | OpCode::Equal => {
+            body.push(format!("let a = {};", read(address, 0, mode(0))));
+            body.push(format!("let b = {};", read(address, 1, mode(1))));
+            body.push(format!("let to = {};", target(address, 2, mode(2))));
+            body.push(match instruction.op_code {
+                OpCode::Add => "self.set(to, a.wrapping_add(b))?;".to_string(),
+                OpCode::Multiply => "self.set(to, a.wrapping_mul(b))?;".to_string(),
+                OpCode::LessThan => "self.set(to, if a < b { 1 } else { 0 })?;".to_string(),
+                _ => "self.set(to, if a == b { 1 } else { 0 })?;".to_string(),
+            });
+            body.push(format!("self.pc = {};", next));
+        }
+        OpCode::Input => {
+            body.push(format!("let to = {};", target(address, 0, mode(0))));
+            body.push("let val = match self.input.front() {".to_string());
+            body.push("    Some(val) => *val,".to_string());
+            body.push("    None => return Ok(RunStatus::NeedsInput),".to_string());
+            body.push("};".to_string());
+            body.push("self.set(to, val)?;".to_string());
+            body.push("self.input.pop_front();".to_string());
+            body.push(format!("self.pc = {};", next));
+        }
+        OpCode::Output => {
+            body.push(format!("let a = {};", read(address, 0, mode(0))));
+            body.push("self.output.push(a);".to_string());
+            body.push(format!("self.pc = {};", next));
+            body.push("return Ok(RunStatus::Output(a));".to_string());
+        }
+        OpCode::JumpIfTrue | OpCode::JumpIfFalse => {
+            let test = if instruction.op_code == OpCode::JumpIfTrue {
+                "!="
+            } else {
+                "=="
+            };
+            body.push(format!("let a = {};", read(address, 0, mode(0))));
+            body.push(format!("self.pc = if a {} 0 {{", test));
+            body.push(format!("    let b = {};", read(address, 1, mode(1))));
+            body.push("    self.to_position(b)?".to_string());
+            body.push("} else {".to_string());
+            body.push(format!("    {}", next));
+            body.push("};".to_string());
+        }
+        OpCode::ChangeRelative => {
+            body.push(format!("let a = {};", read(address, 0, mode(0))));
+            body.push("self.relative_base = match self.relative_base.checked_add(a) {".to_string());
+            body.push("    Some(relative_base) => relative_base,".to_string());
+            body.push(format!(
+                "    None => return Err(VmError::Overflow {{ address: {} }}),",
+                address
+            ));
+            body.push("};".to_string());
+            body.push(format!("self.pc = {};", next));
+        }
+        OpCode::Exit => {
+            body.push("self.finished = true;".to_string());
+            body.push("return Ok(RunStatus::Halted);".to_string());
+        }
+    }
+
+    writeln!(out, "                // {}", instruction).unwrap();
+    writeln!(
+        out,
+        "                {} if self.memory[{}] == {} => {{",
+        address, address, word
+    )
+    .unwrap();
+    for line in body {
+        writeln!(out, "                    {}", line).unwrap();
+    }
+    writeln!(out, "                }}").unwrap();
+}
+
+// Rust source of a module defining the struct name that runs program
+pub fn transpile(program: &[i64], name: &str) -> String {
+    let instructions = instructions(program);
+    let mut out = String::new();
+
+    writeln!(out, "// Generated by intcode::transpile, do not edit.").unwrap();
+    writeln!(out, "#![allow(dead_code)]").unwrap();
+    writeln!(
+        out,
+        "use intcode::{{DenseMemory, MemHandler, RunStatus, Snapshot, VmError, DEFAULT_MEMORY_LIMIT}};"
+    )
+    .unwrap();
+    writeln!(out, "use std::collections::VecDeque;").unwrap();
+    writeln!(out).unwrap();
+
+    let words: Vec<String> = program.iter().map(|w| w.to_string()).collect();
+    writeln!(out, "const PROGRAM: [i64; {}] = [", program.len()).unwrap();
+    for line in words.chunks(16) {
+        writeln!(out, "    {},", line.join(", ")).unwrap();
+    }
+    writeln!(out, "];").unwrap();
+    writeln!(out).unwrap();
+
+    out.push_str(&RUNTIME.replace("NAME", name));
+
+    writeln!(out).unwrap();
+    writeln!(
+        out,
+        "    pub fn run_until_event(&mut self) -> Result<RunStatus, VmError> {{"
+    )
+    .unwrap();
+    writeln!(out, "        loop {{").unwrap();
+    writeln!(
+        out,
+        "            if let Some(interpreter) = &mut self.interpreter {{"
+    )
+    .unwrap();
+    writeln!(out, "                return interpreter.run_until_event();").unwrap();
+    writeln!(out, "            }}").unwrap();
+    writeln!(out, "            if self.finished {{").unwrap();
+    writeln!(out, "                return Ok(RunStatus::Halted);").unwrap();
+    writeln!(out, "            }}").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "            match self.pc {{").unwrap();
+    for (address, instruction) in instructions.iter() {
+        arm(&mut out, *address, program[*address], instruction);
+    }
+    writeln!(out, "                _ => self.start_interpreter(),").unwrap();
+    writeln!(out, "            }}").unwrap();
+    writeln!(out, "        }}").unwrap();
+    writeln!(out, "    }}").unwrap();
+    writeln!(out, "}}").unwrap();
+
+    out
+}
+
+#[test]
+fn test_instructions() {
+    // Everything that decodes, except the write to an immediate at 7
+    let program = vec![1105, 1, 4, 7, 104, 5, 99, 11101, 1, 2, 3];
+    let instructions = instructions(&program);
+    let addresses: Vec<usize> = instructions.iter().map(|(address, _)| *address).collect();
+    assert!(addresses == vec![0, 1, 2, 3, 4, 5, 6]);
+}
+
+#[test]
+fn test_transpile() {
+    let source = transpile(&[1101, 2, 3, 5, 99, 0], "Adder");
+    assert!(source.contains("const PROGRAM: [i64; 6] = [\n    1101, 2, 3, 5, 99, 0,\n];"));
+    assert!(source.contains("pub struct Adder {"));
+    assert!(source.contains(
+        "                // ADD #2, #3 -> [5]
+                0 if self.memory[0] == 1101 => {
+                    let a = self.get(1);
+                    let b = self.get(2);
+                    let to = self.position(3)?;
+                    self.set(to, a.wrapping_add(b))?;
+                    self.pc = 4;
+                }
+"
+    ));
+    assert!(source.contains("                4 if self.memory[4] == 99 => {"));
+
+    let source = transpile(&[109, -1, 99], "Rebase");
+    assert!(source.contains(
+        "                    let a = self.get(1);
+                    self.relative_base = match self.relative_base.checked_add(a) {
+                        Some(relative_base) => relative_base,
+                        None => return Err(VmError::Overflow { address: 0 }),
+                    };
+"
+    ));
+}
@@ -0,0 +1,146 @@
+// Watchpoints on memory accesses made by the program. A watchpoint covers a
+// range of addresses and fires on reads, writes or both, either calling a
+// callback or pausing execution with RunStatus::Watch.
+//
+// Only parameters read from or written to memory count as accesses, reading
+// the instruction itself does not.
+use std::fmt;
+use std::ops::RangeInclusive;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Access {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+impl Access {
+    fn covers(self, access: Access) -> bool {
+        self == Access::ReadWrite || self == access
+    }
+}
+
+// A single access that hit a watchpoint
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WatchHit<W = i64> {
+    // Address of the instruction making the access
+    pub pc: usize,
+    pub address: usize,
+    pub access: Access,
+    // Value read, or written
+    pub value: W,
+}
+
+impl<W: fmt::Display> fmt::Display for WatchHit<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.access {
+            Access::Write => write!(f, "write of {} to {}", self.value, self.address)?,
+            _ => write!(f, "read of {} from {}", self.value, self.address)?,
+        }
+        write!(f, " by instruction at {}", self.pc)
+    }
+}
+
+type Callback<W> = Box<dyn FnMut(&WatchHit<W>) + Send>;
+
+pub enum WatchAction<W = i64> {
+    // Execution stops after the accessing instruction, see RunStatus::Watch
+    Pause,
+    Callback(Callback<W>),
+}
+
+struct Watchpoint<W> {
+    id: usize,
+    range: RangeInclusive<usize>,
+    access: Access,
+    action: WatchAction<W>,
+}
+
+pub(crate) struct Watchpoints<W> {
+    watchpoints: Vec<Watchpoint<W>>,
+    next_id: usize,
+}
+
+impl<W> Default for Watchpoints<W> {
+    fn default() -> Self {
+        Watchpoints {
+            watchpoints: vec![],
+            next_id: 0,
+        }
+    }
+}
+
+impl<W> Watchpoints<W> {
+    pub fn add(
+        &mut self,
+        range: RangeInclusive<usize>,
+        access: Access,
+        action: WatchAction<W>,
+    ) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.watchpoints.push(Watchpoint {
+            id,
+            range,
+            access,
+            action,
+        });
+        id
+    }
+
+    pub fn remove(&mut self, id: usize) -> bool {
+        let len = self.watchpoints.len();
+        self.watchpoints.retain(|w| w.id != id);
+        self.watchpoints.len() != len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.watchpoints.is_empty()
+    }
+
+    // Runs the callbacks of all watchpoints hit, returns true if any of them
+    // wants execution to pause
+    pub fn check(&mut self, hit: &WatchHit<W>) -> bool {
+        let mut pause = false;
+        for watchpoint in self.watchpoints.iter_mut() {
+            if !watchpoint.range.contains(&hit.address) || !watchpoint.access.covers(hit.access) {
+                continue;
+            }
+            match &mut watchpoint.action {
+                WatchAction::Pause => pause = true,
+                WatchAction::Callback(callback) => callback(hit),
+            }
+        }
+        pause
+    }
+}
+
+#[test]
+fn test_watchpoints() {
+    use std::sync::{Arc, Mutex};
+
+    let hits = Arc::new(Mutex::new(vec![]));
+    let seen = hits.clone();
+    let mut watchpoints = Watchpoints::default();
+    watchpoints.add(
+        10..=20,
+        Access::ReadWrite,
+        WatchAction::Callback(Box::new(move |hit| seen.lock().unwrap().push(*hit))),
+    );
+    let pause = watchpoints.add(15..=15, Access::Write, WatchAction::Pause);
+
+    let hit = |address, access| WatchHit {
+        pc: 0,
+        address,
+        access,
+        value: 1,
+    };
+    assert!(!watchpoints.check(&hit(9, Access::Read)));
+    assert!(!watchpoints.check(&hit(15, Access::Read)));
+    assert!(watchpoints.check(&hit(15, Access::Write)));
+    assert!(hits.lock().unwrap().len() == 2);
+
+    assert!(watchpoints.remove(pause) && !watchpoints.remove(pause));
+    assert!(!watchpoints.check(&hit(15, Access::Write)));
+    assert!(hit(15, Access::Write).to_string() == "write of 1 to 15 by instruction at 0");
+}
@@ -0,0 +1,105 @@
+// Type of the values a program computes with, i.e. what memory holds and what
+// goes through input and output. The puzzles fit in an i64, which is the
+// default everywhere. Programs computing larger numbers can run with i128
+// words, or with BigInt words to get exact results whatever their size.
+//
+// Op codes, addresses and the relative base are still i64 whatever the word
+// type, a word used as one of those has to fit in an i64.
+use crate::arithmetic::Arithmetic;
+use num_bigint::BigInt;
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+pub trait Word: Clone + fmt::Debug + fmt::Display + PartialOrd + FromStr + From<i64> {
+    // Whether MemHandler::widen can continue a machine with these words
+    // using wider ones
+    const CAN_WIDEN: bool;
+
+    // None if the value does not fit in an i64
+    fn to_i64(&self) -> Option<i64>;
+
+    // None if the result overflows in checked or widening mode
+    fn add_with(&self, other: &Self, arithmetic: Arithmetic) -> Option<Self>;
+
+    fn multiply_with(&self, other: &Self, arithmetic: Arithmetic) -> Option<Self>;
+}
+
+macro_rules! fixed_width_word {
+    ($word:ty, $can_widen:expr) => {
+        impl Word for $word {
+            const CAN_WIDEN: bool = $can_widen;
+
+            fn to_i64(&self) -> Option<i64> {
+                i64::try_from(*self).ok()
+            }
+
+            fn add_with(&self, other: &Self, arithmetic: Arithmetic) -> Option<Self> {
+                match arithmetic {
+                    Arithmetic::Wrapping => Some(self.wrapping_add(*other)),
+                    Arithmetic::Checked | Arithmetic::Widening => self.checked_add(*other),
+                    Arithmetic::Saturating => Some(self.saturating_add(*other)),
+                }
+            }
+
+            fn multiply_with(&self, other: &Self, arithmetic: Arithmetic) -> Option<Self> {
+                match arithmetic {
+                    Arithmetic::Wrapping => Some(self.wrapping_mul(*other)),
+                    Arithmetic::Checked | Arithmetic::Widening => self.checked_mul(*other),
+                    Arithmetic::Saturating => Some(self.saturating_mul(*other)),
+                }
+            }
+        }
+    };
+}
+
+fixed_width_word!(i64, true);
+fixed_width_word!(i128, false);
+
+// Never overflows, so the arithmetic policy does not matter
+impl Word for BigInt {
+    const CAN_WIDEN: bool = false;
+
+    fn to_i64(&self) -> Option<i64> {
+        i64::try_from(self).ok()
+    }
+
+    fn add_with(&self, other: &Self, _: Arithmetic) -> Option<Self> {
+        Some(self + other)
+    }
+
+    fn multiply_with(&self, other: &Self, _: Arithmetic) -> Option<Self> {
+        Some(self * other)
+    }
+}
+
+#[test]
+fn test_arithmetic() {
+    let big = i64::MAX - 1;
+    assert!(big.add_with(&3, Arithmetic::Wrapping) == Some(i64::MIN + 1));
+    assert!(big.add_with(&3, Arithmetic::Checked).is_none());
+    assert!(big.add_with(&1, Arithmetic::Checked) == Some(i64::MAX));
+    assert!(big.add_with(&3, Arithmetic::Saturating) == Some(i64::MAX));
+
+    assert!(big.multiply_with(&2, Arithmetic::Wrapping) == Some(-4));
+    assert!(big.multiply_with(&-2, Arithmetic::Checked).is_none());
+    assert!(big.multiply_with(&-2, Arithmetic::Saturating) == Some(i64::MIN));
+    assert!(big.multiply_with(&-2, Arithmetic::Widening).is_none());
+    assert!(34915192_i64.multiply_with(&34915192, Arithmetic::Checked) == Some(1219070632396864));
+}
+
+#[test]
+fn test_wide_words() {
+    let big = i64::MAX as i128;
+    assert!(big.multiply_with(&big, Arithmetic::Checked) == Some(big * big));
+    assert!((big * big).to_i64().is_none() && (-big).to_i64() == Some(-i64::MAX));
+    assert!(i128::MAX.add_with(&1, Arithmetic::Checked).is_none());
+
+    let exact = BigInt::from(i128::MAX);
+    let square = exact.multiply_with(&exact, Arithmetic::Checked).unwrap();
+    assert!(
+        square.to_string()
+            == "28948022309329048855892746252171976962977213799489202546401021394546514198529"
+    );
+    assert!(square.to_i64().is_none() && BigInt::from(-7).to_i64() == Some(-7));
+}
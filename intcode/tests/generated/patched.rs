@@ -0,0 +1,298 @@
+// Generated by intcode::transpile, do not edit.
+#![allow(dead_code)]
+use intcode::{DenseMemory, MemHandler, RunStatus, Snapshot, VmError, DEFAULT_MEMORY_LIMIT};
+use std::collections::VecDeque;
+
+const PROGRAM: [i64; 26] = [
+    109, 0, 109, 30, 203, 0, 1002, 30, 2, 31, 204, 1, 1008, 30, 0, 32,
+    1006, 32, 4, 1101, 0, 104, 23, 99, 7, 99,
+];
+
+
+pub struct Patched {
+    memory: Vec<i64>,
+    pc: usize,
+    relative_base: i64,
+    input: VecDeque<i64>,
+    output: Vec<i64>,
+    finished: bool,
+    interpreter: Option<MemHandler>,
+}
+
+impl Default for Patched {
+    fn default() -> Self {
+        Patched::new()
+    }
+}
+
+impl Patched {
+    pub fn new() -> Self {
+        Patched {
+            memory: PROGRAM.to_vec(),
+            pc: 0,
+            relative_base: 0,
+            input: VecDeque::new(),
+            output: vec![],
+            finished: false,
+            interpreter: None,
+        }
+    }
+
+    pub fn add_input(&mut self, input: i64) {
+        match &mut self.interpreter {
+            Some(interpreter) => interpreter.add_input(input),
+            None => self.input.push_back(input),
+        }
+    }
+
+    pub fn get_result(&self) -> Option<Vec<i64>> {
+        match &self.interpreter {
+            Some(interpreter) => interpreter.get_result(),
+            None if self.output.is_empty() => None,
+            None => Some(self.output.clone()),
+        }
+    }
+
+    pub fn last_result(&self) -> Option<i64> {
+        match &self.interpreter {
+            Some(interpreter) => interpreter.last_result(),
+            None => self.output.last().cloned(),
+        }
+    }
+
+    pub fn finished(&self) -> bool {
+        match &self.interpreter {
+            Some(interpreter) => interpreter.finished(),
+            None => self.finished,
+        }
+    }
+
+    pub fn peek(&self, address: usize) -> i64 {
+        match &self.interpreter {
+            Some(interpreter) => interpreter.peek(address),
+            None => self.get(address),
+        }
+    }
+
+    pub fn poke(&mut self, address: usize, val: i64) -> Result<(), VmError> {
+        match &mut self.interpreter {
+            Some(interpreter) => interpreter.poke(address, val),
+            None => self.set(address, val),
+        }
+    }
+
+    pub fn memory(&self) -> &[i64] {
+        match &self.interpreter {
+            Some(interpreter) => interpreter.memory(),
+            None => &self.memory,
+        }
+    }
+
+    pub fn pc(&self) -> usize {
+        match &self.interpreter {
+            Some(interpreter) => interpreter.pc(),
+            None => self.pc,
+        }
+    }
+
+    // True once the program has been handed over to the interpreter
+    pub fn interpreted(&self) -> bool {
+        self.interpreter.is_some()
+    }
+
+    pub fn run_to_halt(&mut self) -> Result<RunStatus, VmError> {
+        loop {
+            match self.run_until_event()? {
+                RunStatus::Output(_) => continue,
+                status => return Ok(status),
+            }
+        }
+    }
+
+    fn get(&self, address: usize) -> i64 {
+        self.memory.get(address).cloned().unwrap_or(0)
+    }
+
+    fn set(&mut self, address: usize, val: i64) -> Result<(), VmError> {
+        if address >= DEFAULT_MEMORY_LIMIT {
+            return Err(VmError::OutOfMemory {
+                address: self.pc,
+                position: address,
+            });
+        }
+        if address >= self.memory.len() {
+            self.memory.resize(address + 1, 0);
+        }
+        self.memory[address] = val;
+        Ok(())
+    }
+
+    fn to_position(&self, position: i64) -> Result<usize, VmError> {
+        if position < 0 {
+            return Err(VmError::NegativeAddress {
+                address: self.pc,
+                position,
+            });
+        }
+        Ok(position as usize)
+    }
+
+    // Address given by the position mode parameter at address
+    fn position(&self, address: usize) -> Result<usize, VmError> {
+        self.to_position(self.get(address))
+    }
+
+    // Address given by the relative mode parameter at address, one beyond
+    // any i64 is reported like MemHandler does
+    fn relative(&self, address: usize) -> Result<usize, VmError> {
+        let word = self.get(address);
+        match word.checked_add(self.relative_base) {
+            Some(position) => self.to_position(position),
+            None if word < 0 => Err(VmError::NegativeAddress {
+                address: self.pc,
+                position: i64::MIN,
+            }),
+            None => Err(VmError::OutOfMemory {
+                address: self.pc,
+                position: usize::MAX,
+            }),
+        }
+    }
+
+    fn start_interpreter(&mut self) {
+        let snapshot = Snapshot {
+            memory: DenseMemory::from(std::mem::take(&mut self.memory)),
+            pc: self.pc,
+            relative_base: self.relative_base,
+            input: self.input.drain(..).collect(),
+            output: std::mem::take(&mut self.output),
+            finished: self.finished,
+        };
+        self.interpreter = Some(MemHandler::from_snapshot(&snapshot));
+    }
+
+    pub fn run_until_event(&mut self) -> Result<RunStatus, VmError> {
+        loop {
+            if let Some(interpreter) = &mut self.interpreter {
+                return interpreter.run_until_event();
+            }
+            if self.finished {
+                return Ok(RunStatus::Halted);
+            }
+
+            match self.pc {
+                // ARB #0
+                0 if self.memory[0] == 109 => {
+                    let a = self.get(1);
+                    self.relative_base = match self.relative_base.checked_add(a) {
+                        Some(relative_base) => relative_base,
+                        None => return Err(VmError::Overflow { address: 0 }),
+                    };
+                    self.pc = 2;
+                }
+                // ARB #30
+                2 if self.memory[2] == 109 => {
+                    let a = self.get(3);
+                    self.relative_base = match self.relative_base.checked_add(a) {
+                        Some(relative_base) => relative_base,
+                        None => return Err(VmError::Overflow { address: 2 }),
+                    };
+                    self.pc = 4;
+                }
+                // IN -> [r+0]
+                4 if self.memory[4] == 203 => {
+                    let to = self.relative(5)?;
+                    let val = match self.input.front() {
+                        Some(val) => *val,
+                        None => return Ok(RunStatus::NeedsInput),
+                    };
+                    self.set(to, val)?;
+                    self.input.pop_front();
+                    self.pc = 6;
+                }
+                // MUL [30], #2 -> [31]
+                6 if self.memory[6] == 1002 => {
+                    let a = self.get(self.position(7)?);
+                    let b = self.get(8);
+                    let to = self.position(9)?;
+                    self.set(to, a.wrapping_mul(b))?;
+                    self.pc = 10;
+                }
+                // MUL [31], [204] -> [1]
+                8 if self.memory[8] == 2 => {
+                    let a = self.get(self.position(9)?);
+                    let b = self.get(self.position(10)?);
+                    let to = self.position(11)?;
+                    self.set(to, a.wrapping_mul(b))?;
+                    self.pc = 12;
+                }
+                // OUT [r+1]
+                10 if self.memory[10] == 204 => {
+                    let a = self.get(self.relative(11)?);
+                    self.output.push(a);
+                    self.pc = 12;
+                    return Ok(RunStatus::Output(a));
+                }
+                // ADD [1008], [30] -> [0]
+                11 if self.memory[11] == 1 => {
+                    let a = self.get(self.position(12)?);
+                    let b = self.get(self.position(13)?);
+                    let to = self.position(14)?;
+                    self.set(to, a.wrapping_add(b))?;
+                    self.pc = 15;
+                }
+                // EQ [30], #0 -> [32]
+                12 if self.memory[12] == 1008 => {
+                    let a = self.get(self.position(13)?);
+                    let b = self.get(14);
+                    let to = self.position(15)?;
+                    self.set(to, if a == b { 1 } else { 0 })?;
+                    self.pc = 16;
+                }
+                // JF [32], #4
+                16 if self.memory[16] == 1006 => {
+                    let a = self.get(self.position(17)?);
+                    self.pc = if a == 0 {
+                        let b = self.get(18);
+                        self.to_position(b)?
+                    } else {
+                        19
+                    };
+                }
+                // OUT [1101]
+                18 if self.memory[18] == 4 => {
+                    let a = self.get(self.position(19)?);
+                    self.output.push(a);
+                    self.pc = 20;
+                    return Ok(RunStatus::Output(a));
+                }
+                // ADD #0, #104 -> [23]
+                19 if self.memory[19] == 1101 => {
+                    let a = self.get(20);
+                    let b = self.get(21);
+                    let to = self.position(22)?;
+                    self.set(to, a.wrapping_add(b))?;
+                    self.pc = 23;
+                }
+                // OUT #23
+                21 if self.memory[21] == 104 => {
+                    let a = self.get(22);
+                    self.output.push(a);
+                    self.pc = 23;
+                    return Ok(RunStatus::Output(a));
+                }
+                // HLT
+                23 if self.memory[23] == 99 => {
+                    self.finished = true;
+                    return Ok(RunStatus::Halted);
+                }
+                // HLT
+                25 if self.memory[25] == 99 => {
+                    self.finished = true;
+                    return Ok(RunStatus::Halted);
+                }
+                _ => self.start_interpreter(),
+            }
+        }
+    }
+}
@@ -0,0 +1,97 @@
+// Runs transpiled code side by side with the interpreter. The generated
+// module is checked in, test_generated fails when it no longer matches what
+// transpile produces, regenerate it with
+//
+//     cargo run --bin transpile -- patched.dat Patched tests/generated/patched.rs
+//
+// where patched.dat holds PROGRAM.
+use intcode::{transpile, MemHandler, RunStatus, VmError};
+
+#[rustfmt::skip]
+#[path = "generated/patched.rs"]
+mod patched;
+use patched::Patched;
+
+// Doubles every input until it gets a 0, then patches the exit at 23 into an
+// output of 7
+const PROGRAM: [i64; 26] = [
+    109, 0, 109, 30, 203, 0, 1002, 30, 2, 31, 204, 1, 1008, 30, 0, 32, 1006, 32, 4, 1101, 0, 104,
+    23, 99, 7, 99,
+];
+
+type Run = (Result<RunStatus, VmError>, Option<Vec<i64>>, Vec<i64>);
+
+// Status, output and memory after running with the patches and input, both
+// transpiled and interpreted
+fn run_both(patches: &[(usize, i64)], input: &[i64]) -> (Run, Run, bool) {
+    let mut native = Patched::new();
+    let mut mem_handler = MemHandler::new(PROGRAM.to_vec());
+    for (address, val) in patches {
+        native.poke(*address, *val).unwrap();
+        mem_handler.poke(*address, *val).unwrap();
+    }
+    for val in input {
+        native.add_input(*val);
+        mem_handler.add_input(*val);
+    }
+
+    let status = native.run_to_halt();
+    let native_run = (status, native.get_result(), native.memory().to_vec());
+    let status = mem_handler.run_to_halt();
+    let interpreted = (
+        status,
+        mem_handler.get_result(),
+        mem_handler.memory().to_vec(),
+    );
+    (native_run, interpreted, native.interpreted())
+}
+
+#[test]
+fn test_generated() {
+    let source = transpile::transpile(&PROGRAM, "Patched");
+    assert!(source == include_str!("generated/patched.rs"));
+}
+
+#[test]
+fn test_self_modified() {
+    // The patched exit is run by the interpreter
+    let (native, interpreted, handed_over) = run_both(&[], &[3, 5, 0]);
+    assert!(native == interpreted && handed_over);
+    assert!(native.0 == Ok(RunStatus::Halted));
+    assert!(native.1 == Some(vec![6, 10, 0, 7]));
+
+    // Waiting for input, everything so far ran natively
+    let (native, interpreted, handed_over) = run_both(&[], &[3]);
+    assert!(native == interpreted && !handed_over);
+    assert!(native.0 == Ok(RunStatus::NeedsInput));
+}
+
+#[test]
+fn test_faults() {
+    // The relative base overflows in the second adjust
+    let (native, interpreted, _) = run_both(&[(1, i64::MAX)], &[]);
+    assert!(native == interpreted);
+    assert!(native.0 == Err(VmError::Overflow { address: 2 }));
+
+    // The relative input address is beyond any i64
+    let (native, interpreted, _) = run_both(&[(1, i64::MAX - 30), (5, 1)], &[]);
+    assert!(native == interpreted);
+    assert!(
+        native.0
+            == Err(VmError::OutOfMemory {
+                address: 4,
+                position: usize::MAX
+            })
+    );
+
+    // And below it
+    let (native, interpreted, _) = run_both(&[(1, i64::MIN), (3, 0), (5, -1)], &[]);
+    assert!(native == interpreted);
+    assert!(
+        native.0
+            == Err(VmError::NegativeAddress {
+                address: 4,
+                position: i64::MIN
+            })
+    );
+}